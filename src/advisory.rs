@@ -0,0 +1,755 @@
+// Vulnerability advisories loaded from local copies of the OSV and NVD databases, normalised
+// into a single form that packages can be matched against.
+
+use crate::error::Error;
+use crate::version::compare_versions;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// OSV ecosystems and the purl type of their packages. Distribution ecosystems like Debian are
+/// left out on purpose, their versions describe distribution builds rather than upstream
+/// releases.
+const OSV_ECOSYSTEMS: &[(&str, &str)] = &[
+    ("PyPI", "pypi"),
+    ("crates.io", "cargo"),
+    ("npm", "npm"),
+    ("Go", "golang"),
+    ("Maven", "maven"),
+    ("RubyGems", "gem"),
+    ("Packagist", "composer"),
+    ("NuGet", "nuget"),
+    ("Hex", "hex"),
+    ("Pub", "pub"),
+];
+
+/// A single advisory, e.g. an OSV entry or an NVD CVE.
+#[derive(Debug, Clone)]
+pub struct Advisory {
+    pub id: String,
+    /// Other ids of the same vulnerability, e.g. the CVE an OSV entry was published for.
+    pub aliases: Vec<String>,
+    pub summary: Option<String>,
+    /// Where the advisory came from, `osv` or `nvd`.
+    pub source: &'static str,
+    affected: Vec<Affected>,
+}
+
+/// One bound of a version range.
+#[derive(Debug, Clone)]
+struct Bound {
+    version: String,
+    inclusive: bool,
+}
+
+/// Versions between `start` and `end`, a missing bound is unbounded.
+#[derive(Debug, Clone, Default)]
+struct Range {
+    start: Option<Bound>,
+    end: Option<Bound>,
+}
+
+impl Range {
+    fn contains(&self, version: &str) -> bool {
+        let after_start =
+            self.start
+                .as_ref()
+                .is_none_or(|v| match compare_versions(version, &v.version) {
+                    Ordering::Greater => true,
+                    Ordering::Equal => v.inclusive,
+                    Ordering::Less => false,
+                });
+        let before_end =
+            self.end
+                .as_ref()
+                .is_none_or(|v| match compare_versions(version, &v.version) {
+                    Ordering::Less => true,
+                    Ordering::Equal => v.inclusive,
+                    Ordering::Greater => false,
+                });
+
+        after_start && before_end
+    }
+}
+
+/// The versions of one package an advisory applies to.
+#[derive(Debug, Clone)]
+struct Affected {
+    /// What the package is matched by, see `package_keys`.
+    key: String,
+    versions: Vec<String>,
+    ranges: Vec<Range>,
+}
+
+impl Affected {
+    fn contains(&self, version: &str) -> bool {
+        self.versions
+            .iter()
+            .any(|v| compare_versions(version, v) == Ordering::Equal)
+            || self.ranges.iter().any(|v| v.contains(version))
+    }
+}
+
+impl Advisory {
+    /// Whether the advisory applies to the given version of a package known by `keys`.
+    pub(crate) fn affects(&self, keys: &[String], version: &str) -> bool {
+        self.affected
+            .iter()
+            .any(|v| keys.contains(&v.key) && v.contains(version))
+    }
+}
+
+/// Keys a package is matched against advisories by: its name, the ecosystem and name of its
+/// upstream purl and the vendor and product of its CPE name.
+pub(crate) fn package_keys(
+    name: &str,
+    upstream_purl: Option<&str>,
+    cpe: Option<&str>,
+) -> Vec<String> {
+    let mut keys = vec![format!("name:{}", name.to_lowercase())];
+    if let Some(purl) = upstream_purl.and_then(purl_key) {
+        keys.push(purl);
+    }
+    if let Some(cpe) = cpe.and_then(cpe_key) {
+        keys.push(cpe);
+    }
+
+    keys
+}
+
+/// `purl:<type>/<name>` for a purl, without its version.
+fn purl_key(purl: &str) -> Option<String> {
+    let purl = purl.strip_prefix("pkg:")?;
+    let purl = purl.split(['@', '?', '#']).next()?;
+    let (purl_type, name) = purl.split_once('/')?;
+
+    Some(purl_name_key(purl_type, name))
+}
+
+fn purl_name_key(purl_type: &str, name: &str) -> String {
+    let name = match purl_type {
+        // Normalised like PEP 503, which the pypi purl type prescribes
+        "pypi" => name.to_lowercase().replace('_', "-"),
+        _ => name.to_string(),
+    };
+
+    format!("purl:{}/{}", purl_type, name)
+}
+
+/// Splits a CPE 2.3 formatted string into its components, keeping escaped colons.
+fn cpe_components(cpe: &str) -> Vec<String> {
+    let mut components = vec![String::new()];
+    let mut escaped = false;
+
+    for c in cpe.chars() {
+        match c {
+            ':' if !escaped => components.push(String::new()),
+            _ => {
+                escaped = c == '\\' && !escaped;
+                if let Some(last) = components.last_mut() {
+                    last.push(c);
+                }
+            }
+        }
+    }
+
+    components
+}
+
+/// `cpe:<vendor>:<product>` for a CPE 2.3 name.
+fn cpe_key(cpe: &str) -> Option<String> {
+    let components = cpe_components(cpe);
+
+    match components.as_slice() {
+        [_, _, _, vendor, product, ..] => Some(format!("cpe:{}:{}", vendor, product)),
+        _ => None,
+    }
+}
+
+/// A set of advisories, indexed by the keys of the packages they affect.
+#[derive(Debug, Default)]
+pub struct AdvisoryDb {
+    advisories: Vec<Advisory>,
+    by_key: HashMap<String, Vec<usize>>,
+}
+
+impl AdvisoryDb {
+    pub fn new() -> AdvisoryDb {
+        AdvisoryDb::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.advisories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.advisories.is_empty()
+    }
+
+    fn add(&mut self, advisory: Advisory) {
+        let index = self.advisories.len();
+        let mut keys = advisory
+            .affected
+            .iter()
+            .map(|v| v.key.clone())
+            .collect::<Vec<String>>();
+        keys.sort();
+        keys.dedup();
+
+        for key in keys {
+            self.by_key.entry(key).or_default().push(index);
+        }
+        self.advisories.push(advisory);
+    }
+
+    /// Returns the advisories affecting a package known by `keys`, in the order they were loaded.
+    pub(crate) fn candidates(&self, keys: &[String]) -> Vec<&Advisory> {
+        let mut indices = keys
+            .iter()
+            .flat_map(|v| self.by_key.get(v).into_iter().flatten())
+            .copied()
+            .collect::<Vec<usize>>();
+        indices.sort();
+        indices.dedup();
+
+        indices.into_iter().map(|v| &self.advisories[v]).collect()
+    }
+
+    /// Loads OSV advisories from a JSON file, holding a single advisory or a list of them, or
+    /// from every `.json` file below a directory, like an unpacked OSV dump. An entry without an
+    /// `id` means the file isn't OSV at all, which is an error rather than no advisories.
+    pub fn load_osv(&mut self, path: &Path) -> Result<(), Error> {
+        for file in json_files(path)? {
+            let entries = match read_json(&file)? {
+                Value::Array(entries) => entries,
+                entry => vec![entry],
+            };
+            for entry in &entries {
+                if !self.add_osv(entry) {
+                    return Err(Error::InvalidInput(format!(
+                        "{} isn't an OSV advisory, it has an entry without an `id`",
+                        file.display()
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds an OSV entry, returning `false` if it has no `id`. Entries that affect none of the
+    /// ecosystems we can match against are skipped.
+    fn add_osv(&mut self, entry: &Value) -> bool {
+        let id = match entry["id"].as_str() {
+            Some(id) => id.to_string(),
+            None => return false,
+        };
+
+        let affected = entry["affected"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(osv_affected)
+            .collect::<Vec<Affected>>();
+        if !affected.is_empty() {
+            self.add(Advisory {
+                id,
+                aliases: strings(&entry["aliases"]),
+                summary: entry["summary"]
+                    .as_str()
+                    .or_else(|| entry["details"].as_str())
+                    .map(|v| v.to_string()),
+                source: "osv",
+                affected,
+            });
+        }
+
+        true
+    }
+
+    /// Loads CVEs from NVD data: an NVD API 2.0 response such as a page of `/cves/2.0`, a legacy
+    /// JSON 1.1 data feed like an uncompressed `nvdcve-1.1-2021.json`, or every `.json` file
+    /// below a directory. A file in neither format is an error.
+    pub fn load_nvd(&mut self, path: &Path) -> Result<(), Error> {
+        for file in json_files(path)? {
+            let data = read_json(&file)?;
+            if let Some(vulnerabilities) = data["vulnerabilities"].as_array() {
+                for vulnerability in vulnerabilities {
+                    self.add_nvd(&vulnerability["cve"]);
+                }
+            } else if let Some(items) = data["CVE_Items"].as_array() {
+                for item in items {
+                    self.add_nvd_1_1(item);
+                }
+            } else {
+                return Err(Error::InvalidInput(format!(
+                    "{} is neither an NVD API 2.0 response nor an NVD JSON 1.1 feed",
+                    file.display()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds a `cve` object of the NVD API 2.0.
+    fn add_nvd(&mut self, cve: &Value) {
+        let id = match cve["id"].as_str() {
+            Some(id) => id.to_string(),
+            None => return,
+        };
+
+        let mut affected = Vec::new();
+        for node in cve["configurations"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|v| v["nodes"].as_array().into_iter().flatten())
+        {
+            affected.extend(
+                node["cpeMatch"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|v| cpe_match_affected(v, "criteria")),
+            );
+        }
+
+        self.add_nvd_advisory(id, english_description(&cve["descriptions"]), affected);
+    }
+
+    /// Adds an item of an NVD JSON 1.1 feed.
+    fn add_nvd_1_1(&mut self, item: &Value) {
+        let id = match item["cve"]["CVE_data_meta"]["ID"].as_str() {
+            Some(id) => id.to_string(),
+            None => return,
+        };
+
+        let mut affected = Vec::new();
+        for node in item["configurations"]["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            collect_cpe_matches(node, &mut affected);
+        }
+
+        let summary = english_description(&item["cve"]["description"]["description_data"]);
+        self.add_nvd_advisory(id, summary, affected);
+    }
+
+    fn add_nvd_advisory(&mut self, id: String, summary: Option<String>, affected: Vec<Affected>) {
+        if affected.is_empty() {
+            return;
+        }
+
+        self.add(Advisory {
+            id,
+            aliases: Vec::new(),
+            summary,
+            source: "nvd",
+            affected,
+        });
+    }
+}
+
+/// Picks the English description out of a list of `{"lang", "value"}` objects, or the first one.
+fn english_description(descriptions: &Value) -> Option<String> {
+    let descriptions = descriptions.as_array()?;
+
+    descriptions
+        .iter()
+        .find(|v| v["lang"].as_str() == Some("en"))
+        .or_else(|| descriptions.first())
+        .and_then(|v| v["value"].as_str())
+        .map(|v| v.to_string())
+}
+
+/// Returns `path` itself, or all `.json` files below it if it's a directory, sorted.
+fn json_files(path: &Path) -> Result<Vec<std::path::PathBuf>, Error> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries =
+            fs::read_dir(&dir).map_err(|e| Error::io(format!("reading {}", dir.display()), e))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| Error::io(format!("reading {}", dir.display()), e))?
+                .path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|v| v == "json") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+fn read_json(path: &Path) -> Result<Value, Error> {
+    let contents =
+        fs::read(path).map_err(|e| Error::io(format!("reading {}", path.display()), e))?;
+
+    serde_json::from_slice(&contents).map_err(|e| Error::json(path.display().to_string(), e))
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|v| v.as_str())
+        .map(|v| v.to_string())
+        .collect()
+}
+
+/// Normalises an OSV `affected` entry, `None` for ecosystems we can't match against.
+fn osv_affected(affected: &Value) -> Option<Affected> {
+    let package = &affected["package"];
+    let name = package["name"].as_str()?;
+    let ecosystem = package["ecosystem"].as_str().unwrap_or_default();
+
+    let key = match package["purl"].as_str().and_then(purl_key) {
+        Some(key) => key,
+        None => match OSV_ECOSYSTEMS.iter().find(|(v, _)| *v == ecosystem) {
+            Some((_, purl_type)) => purl_name_key(purl_type, name),
+            // OSS-Fuzz names projects after upstream, like nixpkgs mostly does
+            None if ecosystem == "OSS-Fuzz" => format!("name:{}", name.to_lowercase()),
+            None => return None,
+        },
+    };
+
+    // Events are ordered, each `introduced` opens a range that a `fixed` or `last_affected`
+    // closes. Git ranges are in commits, which can't be compared to versions.
+    let mut ranges = Vec::new();
+    for range in affected["ranges"].as_array().into_iter().flatten() {
+        if range["type"].as_str() == Some("GIT") {
+            continue;
+        }
+
+        let mut open: Option<Range> = None;
+        for event in range["events"].as_array().into_iter().flatten() {
+            if let Some(version) = event["introduced"].as_str() {
+                open = Some(Range {
+                    start: Some(version).filter(|v| *v != "0").map(|v| Bound {
+                        version: v.to_string(),
+                        inclusive: true,
+                    }),
+                    end: None,
+                });
+            } else if let Some(mut range) = open.take() {
+                range.end = match (event["fixed"].as_str(), event["last_affected"].as_str()) {
+                    (Some(version), _) => Some(Bound {
+                        version: version.to_string(),
+                        inclusive: false,
+                    }),
+                    (_, Some(version)) => Some(Bound {
+                        version: version.to_string(),
+                        inclusive: true,
+                    }),
+                    _ => None,
+                };
+                ranges.push(range);
+            }
+        }
+        ranges.extend(open);
+    }
+
+    Some(Affected {
+        key,
+        versions: strings(&affected["versions"]),
+        ranges,
+    })
+}
+
+/// Normalises a vulnerable NVD CPE match, whose CPE name is in `uri_attribute`: `cpe23Uri` in
+/// JSON 1.1 feeds, `criteria` in API 2.0 responses.
+fn cpe_match_affected(cpe_match: &Value, uri_attribute: &str) -> Option<Affected> {
+    if cpe_match["vulnerable"].as_bool() != Some(true) {
+        return None;
+    }
+
+    let uri = cpe_match[uri_attribute].as_str().unwrap_or_default();
+    let components = cpe_components(uri);
+    let key = cpe_key(uri)?;
+    let version = components.get(5)?;
+
+    let bound = |attribute: &str, inclusive| {
+        cpe_match[attribute].as_str().map(|v| Bound {
+            version: v.to_string(),
+            inclusive,
+        })
+    };
+    let range = Range {
+        start: bound("versionStartIncluding", true)
+            .or_else(|| bound("versionStartExcluding", false)),
+        end: bound("versionEndIncluding", true).or_else(|| bound("versionEndExcluding", false)),
+    };
+
+    match version.as_str() {
+        // Any version, as far as the bounds allow
+        "*" => Some(Affected {
+            key,
+            versions: Vec::new(),
+            ranges: vec![range],
+        }),
+        // Not applicable, there's no version to compare
+        "-" => None,
+        version => Some(Affected {
+            key,
+            versions: vec![version.replace('\\', "")],
+            ranges: Vec::new(),
+        }),
+    }
+}
+
+/// Collects the vulnerable CPE matches of an NVD JSON 1.1 configuration node and its children.
+fn collect_cpe_matches(node: &Value, affected: &mut Vec<Affected>) {
+    affected.extend(
+        node["cpe_match"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|v| cpe_match_affected(v, "cpe23Uri")),
+    );
+
+    for child in node["children"].as_array().into_iter().flatten() {
+        collect_cpe_matches(child, affected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/advisories");
+    const BASH_CPE: &str = "cpe:2.3:a:gnu:bash:4.4-p23:*:*:*:*:*:*:*";
+
+    fn osv(events: Value) -> AdvisoryDb {
+        let mut db = AdvisoryDb::new();
+        assert!(db.add_osv(&json!({
+            "id": "GHSA-0000-0000-0000",
+            "affected": [{
+                "package": {"name": "Requests_Toolbelt", "ecosystem": "PyPI"},
+                "ranges": [{"type": "ECOSYSTEM", "events": events}],
+            }],
+        })));
+
+        db
+    }
+
+    fn osv_affects(db: &AdvisoryDb, version: &str) -> bool {
+        let keys = package_keys(
+            "requests-toolbelt",
+            Some("pkg:pypi/requests-toolbelt"),
+            None,
+        );
+
+        db.candidates(&keys)
+            .iter()
+            .any(|v| v.affects(&keys, version))
+    }
+
+    fn nvd(cpe_match: Value) -> AdvisoryDb {
+        let mut db = AdvisoryDb::new();
+        db.add_nvd(&json!({
+            "id": "CVE-2021-0001",
+            "configurations": [{"nodes": [{"cpeMatch": [cpe_match]}]}],
+        }));
+
+        db
+    }
+
+    fn nvd_affects(db: &AdvisoryDb, version: &str) -> bool {
+        let keys = package_keys("curl", None, Some("cpe:2.3:a:haxx:curl:*:*:*:*:*:*:*:*"));
+
+        db.candidates(&keys)
+            .iter()
+            .any(|v| v.affects(&keys, version))
+    }
+
+    fn ids(db: &AdvisoryDb) -> Vec<&str> {
+        db.advisories.iter().map(|v| v.id.as_str()).collect()
+    }
+
+    #[test]
+    fn osv_fixed_ends_a_range_exclusively() {
+        let db = osv(json!([{"introduced": "1.0"}, {"fixed": "1.2"}]));
+
+        assert!(!osv_affects(&db, "0.9"));
+        assert!(osv_affects(&db, "1.0"));
+        assert!(osv_affects(&db, "1.1.5"));
+        assert!(!osv_affects(&db, "1.2"));
+    }
+
+    #[test]
+    fn osv_last_affected_ends_a_range_inclusively() {
+        let db = osv(json!([{"introduced": "2.0"}, {"last_affected": "2.1"}]));
+
+        assert!(osv_affects(&db, "2.1"));
+        assert!(!osv_affects(&db, "2.1.1"));
+    }
+
+    #[test]
+    fn osv_introduced_zero_and_open_ranges_are_unbounded() {
+        let db = osv(json!([
+            {"introduced": "0"},
+            {"fixed": "0.5"},
+            {"introduced": "3.0"},
+        ]));
+
+        assert!(osv_affects(&db, "0.0.1"));
+        assert!(!osv_affects(&db, "1.0"));
+        assert!(osv_affects(&db, "99"));
+    }
+
+    #[test]
+    fn osv_matches_listed_versions_and_skips_git_ranges() {
+        let mut db = AdvisoryDb::new();
+        db.add_osv(&json!({
+            "id": "GHSA-0000-0000-0001",
+            "affected": [{
+                "package": {"name": "requests-toolbelt", "ecosystem": "PyPI"},
+                "ranges": [{"type": "GIT", "events": [{"introduced": "0"}]}],
+                "versions": ["0.9.1"],
+            }],
+        }));
+
+        assert!(osv_affects(&db, "0.9.1"));
+        assert!(!osv_affects(&db, "0.9.2"));
+    }
+
+    #[test]
+    fn nvd_start_and_end_bounds() {
+        let db = nvd(json!({
+            "vulnerable": true,
+            "criteria": "cpe:2.3:a:haxx:curl:*:*:*:*:*:*:*:*",
+            "versionStartIncluding": "7.10",
+            "versionEndExcluding": "7.76.1",
+        }));
+        assert!(!nvd_affects(&db, "7.9"));
+        assert!(nvd_affects(&db, "7.10"));
+        assert!(nvd_affects(&db, "7.76.0"));
+        assert!(!nvd_affects(&db, "7.76.1"));
+
+        let db = nvd(json!({
+            "vulnerable": true,
+            "criteria": "cpe:2.3:a:haxx:curl:*:*:*:*:*:*:*:*",
+            "versionStartExcluding": "7.10",
+            "versionEndIncluding": "7.76.1",
+        }));
+        assert!(!nvd_affects(&db, "7.10"));
+        assert!(nvd_affects(&db, "7.10.1"));
+        assert!(nvd_affects(&db, "7.76.1"));
+        assert!(!nvd_affects(&db, "7.77.0"));
+    }
+
+    #[test]
+    fn nvd_exact_versions() {
+        let db = nvd(json!({
+            "vulnerable": true,
+            "criteria": "cpe:2.3:a:haxx:curl:7.76.1:*:*:*:*:*:*:*",
+        }));
+
+        assert!(nvd_affects(&db, "7.76.1"));
+        assert!(!nvd_affects(&db, "7.76.0"));
+    }
+
+    #[test]
+    fn nvd_skips_unversioned_and_not_vulnerable_matches() {
+        let db = nvd(json!({
+            "vulnerable": true,
+            "criteria": "cpe:2.3:a:haxx:curl:-:*:*:*:*:*:*:*",
+        }));
+        assert!(db.is_empty());
+
+        let db = nvd(json!({
+            "vulnerable": false,
+            "criteria": "cpe:2.3:a:haxx:curl:*:*:*:*:*:*:*:*",
+        }));
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn nvd_matches_by_vendor_and_product() {
+        let db = nvd(json!({
+            "vulnerable": true,
+            "criteria": "cpe:2.3:a:haxx:curl:*:*:*:*:*:*:*:*",
+        }));
+        let other_vendor = package_keys(
+            "curl",
+            None,
+            Some("cpe:2.3:a:other:curl:7.76.1:*:*:*:*:*:*:*"),
+        );
+        let name_only = package_keys("curl", None, None);
+
+        assert!(nvd_affects(&db, "7.76.1"));
+        assert!(db.candidates(&other_vendor).is_empty());
+        assert!(db.candidates(&name_only).is_empty());
+    }
+
+    #[test]
+    fn loads_nvd_api_2_0_responses() {
+        let mut db = AdvisoryDb::new();
+        db.load_nvd(&Path::new(FIXTURES).join("nvd-2.0.json"))
+            .unwrap();
+        let keys = package_keys("bash", None, Some(BASH_CPE));
+
+        assert_eq!(ids(&db), ["CVE-2019-18276", "CVE-2022-3715"]);
+        assert_eq!(
+            db.advisories[0].summary.as_deref(),
+            Some("An issue was discovered in disable_priv_mode in shell.c in GNU Bash through 5.0 patch 11.")
+        );
+        assert!(db.advisories[0].affects(&keys, "4.4-p23"));
+        assert!(!db.advisories[1].affects(&keys, "4.4-p23"));
+    }
+
+    #[test]
+    fn loads_nvd_json_1_1_feeds() {
+        let mut db = AdvisoryDb::new();
+        db.load_nvd(&Path::new(FIXTURES).join("nvdcve-1.1.json"))
+            .unwrap();
+        let keys = package_keys("bash", None, Some(BASH_CPE));
+
+        assert_eq!(ids(&db), ["CVE-2019-18276", "CVE-2014-6271"]);
+        assert!(db.advisories[0].affects(&keys, "4.4-p23"));
+        assert!(db.advisories[1].affects(&keys, "4.3"));
+        assert!(!db.advisories[1].affects(&keys, "4.4-p23"));
+    }
+
+    #[test]
+    fn loads_osv_files() {
+        let mut db = AdvisoryDb::new();
+        db.load_osv(&Path::new(FIXTURES).join("osv.json")).unwrap();
+
+        assert_eq!(ids(&db), ["OSV-2020-1612", "GHSA-x84v-xcm2-53pg"]);
+        assert_eq!(db.advisories[0].aliases, ["CVE-2019-18276"]);
+        // The Debian entry is left out, only the PyPI one is matched against
+        assert_eq!(db.advisories[1].affected.len(), 1);
+        assert_eq!(db.advisories[1].affected[0].key, "purl:pypi/requests");
+    }
+
+    #[test]
+    fn rejects_files_of_the_other_database() {
+        let mut db = AdvisoryDb::new();
+
+        let error = db
+            .load_nvd(&Path::new(FIXTURES).join("osv.json"))
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidInput(_)), "{:?}", error);
+
+        let error = db
+            .load_osv(&Path::new(FIXTURES).join("nvd-2.0.json"))
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidInput(_)), "{:?}", error);
+    }
+}
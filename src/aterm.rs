@@ -0,0 +1,336 @@
+// Parser for the ATerm serialisation Nix uses for `.drv` files:
+//
+// Derive([("out","/nix/store/…-hello","","")],[("/nix/store/…-bash.drv",["out"])],
+//        ["/nix/store/…-builder.sh"],"x86_64-linux","/nix/store/…-bash/bin/bash",
+//        ["-e","/nix/store/…-builder.sh"],[("name","hello"),…])
+
+use crate::derivation::{Drv, Out};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Where and why a `.drv` file couldn't be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Byte offset into the file.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at byte {}: {}", self.position, self.message)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            position: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if self.input[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", token)))
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect("\"")?;
+        let mut bytes = Vec::new();
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    bytes.push(match escaped {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        c => c,
+                    });
+                }
+                c => bytes.push(c),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| self.error("string is not valid UTF-8"))
+    }
+
+    /// Parses a `[…]` list, calling `item` for every element.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        self.expect("[")?;
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, ParseError> {
+        self.list(|p| p.string())
+    }
+
+    fn output(&mut self) -> Result<(String, Out), ParseError> {
+        self.expect("(")?;
+        let name = self.string()?;
+        self.expect(",")?;
+        let path = self.string()?;
+        self.expect(",")?;
+        let hash_algo = self.string()?;
+        self.expect(",")?;
+        let hash = self.string()?;
+        self.expect(")")?;
+
+        let non_empty = |v: String| if v.is_empty() { None } else { Some(v) };
+        Ok((
+            name,
+            Out {
+                path,
+                hash_algo: non_empty(hash_algo),
+                hash: non_empty(hash),
+            },
+        ))
+    }
+
+    fn input_drv(&mut self) -> Result<(String, Vec<String>), ParseError> {
+        self.expect("(")?;
+        let path = self.string()?;
+        self.expect(",")?;
+        let outputs = self.strings()?;
+        self.expect(")")?;
+
+        Ok((path, outputs))
+    }
+
+    fn env_var(&mut self) -> Result<(String, String), ParseError> {
+        self.expect("(")?;
+        let name = self.string()?;
+        self.expect(",")?;
+        let value = self.string()?;
+        self.expect(")")?;
+
+        Ok((name, value))
+    }
+
+    fn derivation(&mut self) -> Result<Drv, ParseError> {
+        self.expect("Derive(")?;
+        let outputs = self.list(|p| p.output())?;
+        self.expect(",")?;
+        let input_drvs = self.list(|p| p.input_drv())?;
+        self.expect(",")?;
+        let input_srcs = self.strings()?;
+        self.expect(",")?;
+        let system = self.string()?;
+        self.expect(",")?;
+        let builder = self.string()?;
+        self.expect(",")?;
+        let args = self.strings()?;
+        self.expect(",")?;
+        let env = self.list(|p| p.env_var())?;
+        self.expect(")")?;
+
+        if self.pos != self.input.len() {
+            return Err(self.error("trailing data after derivation"));
+        }
+
+        Ok(Drv {
+            outputs: outputs.into_iter().collect(),
+            input_srcs,
+            input_drvs: input_drvs.into_iter().collect(),
+            system,
+            builder,
+            args,
+            env: env.into_iter().collect(),
+            extra: HashMap::new(),
+        })
+    }
+}
+
+/// Parses the contents of a `.drv` file into the same `Drv` that `nix show-derivation` produces.
+pub fn parse_derivation(input: &str) -> Result<Drv, ParseError> {
+    let mut parser = Parser {
+        input: input.trim_end().as_bytes(),
+        pos: 0,
+    };
+
+    parser.derivation()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derivation::DerivationSource;
+    use std::path::PathBuf;
+
+    const HELLO_PATH: &str = "/nix/store/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10.drv";
+    const HELLO: &str = include_str!(
+        "../tests/fixtures/store/nix/store/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10.drv"
+    );
+    const HELLO_JSON: &str = include_str!("../tests/fixtures/hello-2.10.drv.json");
+    const SOURCE_PATH: &str = "/nix/store/jv9gi0wq6j2h5w8kz1qa5zj9m2z6ny2r-source.drv";
+    const SOURCE: &str = include_str!(
+        "../tests/fixtures/store/nix/store/jv9gi0wq6j2h5w8kz1qa5zj9m2z6ny2r-source.drv"
+    );
+    const SOURCE_JSON: &str = include_str!("../tests/fixtures/source.drv.json");
+
+    fn show_derivation(json: &str) -> HashMap<String, Drv> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn store() -> DerivationSource {
+        DerivationSource::Store(PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/store"
+        )))
+    }
+
+    #[test]
+    fn parses_input_addressed_derivation() {
+        let drv = parse_derivation(HELLO).unwrap();
+
+        let out = &drv.outputs["out"];
+        assert_eq!(
+            out.path,
+            "/nix/store/xbhlhfz3lzqcqzm8hf8n9ymzcv7c3g6v-hello-2.10"
+        );
+        assert_eq!(out.hash_algo, None);
+        assert_eq!(out.hash, None);
+        assert_eq!(drv.input_drvs.len(), 3);
+        assert_eq!(
+            drv.input_drvs["/nix/store/6cgi6gbpw7wxbwpf7kbq4xbxxs6s0yn1-bash-4.4-p23.drv"],
+            ["out"]
+        );
+        assert_eq!(drv.input_srcs.len(), 2);
+        assert_eq!(drv.system, "x86_64-linux");
+        assert_eq!(
+            drv.builder,
+            "/nix/store/4nmqxajzaf60yjribkgvj5j54x9yvr1r-bash-4.4-p23/bin/bash"
+        );
+        assert_eq!(drv.args[0], "-e");
+        assert_eq!(drv.env["name"], "hello-2.10");
+        assert!(drv.extra.is_empty());
+    }
+
+    #[test]
+    fn parses_fixed_output_derivation() {
+        let drv = parse_derivation(SOURCE).unwrap();
+
+        let out = &drv.outputs["out"];
+        assert_eq!(out.hash_algo.as_deref(), Some("r:sha256"));
+        assert_eq!(
+            out.hash.as_deref(),
+            Some("4d2a1a5a3ef7b65de48b6ea38c88ec2cbb9e36ab9c7d2d93a4bcdb0bd5df81cc")
+        );
+        assert_eq!(drv.builder, "builtin:fetchurl");
+        assert_eq!(drv.env["outputHashMode"], "recursive");
+    }
+
+    #[test]
+    fn parses_empty_lists() {
+        let drv = parse_derivation(SOURCE).unwrap();
+
+        assert!(drv.input_drvs.is_empty());
+        assert!(drv.input_srcs.is_empty());
+        assert!(drv.args.is_empty());
+
+        let drv = parse_derivation(r#"Derive([],[],[],"","",[],[])"#).unwrap();
+        assert!(drv.outputs.is_empty());
+        assert!(drv.env.is_empty());
+    }
+
+    #[test]
+    fn unescapes_strings() {
+        let drv = parse_derivation(HELLO).unwrap();
+
+        assert_eq!(
+            drv.env["preConfigure"],
+            "echo \"configuring\" in\t$PWD\nsed -i 's/\\\\n/ /' Makefile\n"
+        );
+
+        let drv =
+            parse_derivation(r#"Derive([],[],[],"","",["a\"b","c\\d","e\nf","g\th"],[])"#).unwrap();
+        assert_eq!(drv.args, ["a\"b", "c\\d", "e\nf", "g\th"]);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        for end in [0, 7, HELLO.len() / 2, HELLO.len() - 1] {
+            assert!(parse_derivation(&HELLO[..end]).is_err(), "{}", end);
+        }
+
+        let error = parse_derivation(r#"Derive([],[],[],"x86_64-linux"#).unwrap_err();
+        assert!(error.to_string().contains("unterminated string"));
+    }
+
+    #[test]
+    fn rejects_trailing_data() {
+        let error = parse_derivation(&format!("{}Derive", HELLO)).unwrap_err();
+        assert!(error.to_string().contains("trailing data"));
+
+        // Trailing whitespace is fine, as in a file ending with a newline
+        assert!(parse_derivation(&format!("{}\n", HELLO)).is_ok());
+    }
+
+    #[test]
+    fn store_reports_invalid_derivations() {
+        let path = "/nix/store/p2mhc4k8d0i4yc0kcxbw4fb7ijxav9gb-truncated.drv";
+        let error = store().get_derivation(path).unwrap_err();
+
+        assert!(matches!(
+            &error,
+            crate::Error::InvalidDerivation { drv_path, .. } if drv_path.ends_with(path)
+        ));
+        assert_eq!(error.exit_code(), 7);
+    }
+
+    #[test]
+    fn store_matches_show_derivation() {
+        for (path, json) in [(HELLO_PATH, HELLO_JSON), (SOURCE_PATH, SOURCE_JSON)] {
+            let native = store().get_derivation(path).unwrap();
+            let shown = show_derivation(json);
+
+            assert_eq!(native.len(), 1);
+            assert_eq!(
+                serde_json::to_value(&native).unwrap(),
+                serde_json::to_value(&shown).unwrap(),
+                "{}",
+                path
+            );
+        }
+    }
+}
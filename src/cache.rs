@@ -0,0 +1,156 @@
+// On-disk cache of `nix-env -qa --json` output. Querying all of nixpkgs takes long enough that
+// it's worth keeping around, but only for as long as the nixpkgs it came from doesn't change.
+
+use crate::error::Error;
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever the way entries are written changes, which invalidates all existing entries.
+const FORMAT_VERSION: u32 = 1;
+
+/// Identifies the nixpkgs a set of package metadata was queried from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NixpkgsId {
+    /// Stable identifier of the nixpkgs revision, e.g. its store path hash or flake narHash.
+    pub key: String,
+    /// Changes whenever the contents behind `key` may have changed, e.g. for a git checkout.
+    pub fingerprint: String,
+    /// Human readable description of where nixpkgs came from.
+    pub source: String,
+}
+
+/// What is recorded next to every cached metadata file.
+#[derive(Debug, Serialize, Deserialize)]
+struct EntryInfo {
+    format_version: u32,
+    key: String,
+    fingerprint: String,
+    source: String,
+    created: String,
+}
+
+/// A directory of cached nixpkgs metadata, one entry per nixpkgs revision.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    refresh: bool,
+}
+
+impl Cache {
+    /// Uses `dir`, or `$XDG_CACHE_HOME/nixbom` (falling back to `~/.cache/nixbom`) if `None`.
+    pub fn new(dir: Option<PathBuf>) -> Result<Cache, Error> {
+        let dir = dir.or_else(default_dir).ok_or_else(|| {
+            Error::InvalidInput(
+                "can't determine a cache directory, set XDG_CACHE_HOME or pass --cache-path"
+                    .to_string(),
+            )
+        })?;
+
+        Ok(Cache {
+            dir,
+            refresh: false,
+        })
+    }
+
+    /// Ignores existing entries, while still writing fresh ones.
+    pub fn refresh(mut self, refresh: bool) -> Cache {
+        self.refresh = refresh;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, id: &NixpkgsId, extension: &str) -> PathBuf {
+        let name = id
+            .key
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+
+        self.dir.join(format!("nixpkgs-{}.{}", name, extension))
+    }
+
+    /// Returns the cached metadata for a nixpkgs, or `None` if there is none or it's stale.
+    /// Stale entries are removed.
+    pub fn get(&self, id: &NixpkgsId) -> Result<Option<Vec<u8>>, Error> {
+        if self.refresh {
+            return Ok(None);
+        }
+
+        let info_path = self.entry_path(id, "info.json");
+        let data_path = self.entry_path(id, "json");
+        let info = match fs::read(&info_path) {
+            Ok(info) => serde_json::from_slice::<EntryInfo>(&info).ok(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::io(format!("reading {}", info_path.display()), e)),
+        };
+
+        let is_fresh = info.is_some_and(|v| {
+            v.format_version == FORMAT_VERSION && v.key == id.key && v.fingerprint == id.fingerprint
+        });
+        if !is_fresh {
+            let _ = fs::remove_file(&info_path);
+            let _ = fs::remove_file(&data_path);
+            return Ok(None);
+        }
+
+        match fs::read(&data_path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::io(format!("reading {}", data_path.display()), e)),
+        }
+    }
+
+    /// Stores the metadata for a nixpkgs. The data is written before the info file that makes
+    /// it valid, both atomically, so an interrupted run never leaves a truncated entry behind.
+    pub fn put(&self, id: &NixpkgsId, data: &[u8], created: &str) -> Result<(), Error> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| Error::io(format!("creating {}", self.dir.display()), e))?;
+
+        let info = EntryInfo {
+            format_version: FORMAT_VERSION,
+            key: id.key.clone(),
+            fingerprint: id.fingerprint.clone(),
+            source: id.source.clone(),
+            created: created.to_string(),
+        };
+        let info =
+            serde_json::to_vec_pretty(&info).map_err(|e| Error::json("the cache entry info", e))?;
+
+        write_atomically(&self.entry_path(id, "json"), data)?;
+        write_atomically(&self.entry_path(id, "info.json"), &info)
+    }
+}
+
+fn default_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|v| !v.is_empty())
+                .map(|v| PathBuf::from(v).join(".cache"))
+        })?;
+
+    Some(base.join("nixbom"))
+}
+
+/// Writes to a temporary file next to `path` and renames it into place.
+fn write_atomically(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    };
+
+    write().map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        Error::io(format!("writing {}", path.display()), e)
+    })
+}
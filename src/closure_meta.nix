@@ -0,0 +1,46 @@
+# Evaluates the metadata of just the given nixpkgs attributes, in the format of
+# `nix-env -qa --json --meta --drv-path --out-path`. `attrs` is a JSON list of attribute paths,
+# each a list of names. Attributes that don't exist or fail to evaluate are left out.
+{ nixpkgs ? "", attrs }:
+
+let
+  pkgs = import (if nixpkgs == "" then <nixpkgs> else nixpkgs) {
+    config = {
+      # Whatever the derivation was built with has to be described, allowed or not
+      allowUnfree = true;
+      allowBroken = true;
+      allowUnsupportedSystem = true;
+      allowInsecurePredicate = _: true;
+    };
+  };
+  inherit (pkgs) lib;
+
+  metaAttrs = [
+    "license"
+    "homepage"
+    "description"
+    "maintainers"
+    "position"
+    "knownVulnerabilities"
+  ];
+
+  describe = path:
+    let
+      found = builtins.tryEval (lib.attrByPath path null pkgs);
+      pkg = found.value;
+      parsed = builtins.parseDrvName pkg.name;
+      info = {
+        inherit (pkg) name;
+        pname = pkg.pname or parsed.name;
+        version = pkg.version or parsed.version;
+        drvPath = pkg.drvPath;
+        outputs = lib.genAttrs (pkg.outputs or [ "out" ]) (output: pkg.${output}.outPath);
+        meta = lib.filterAttrs (name: _: builtins.elem name metaAttrs) (pkg.meta or { });
+      };
+      result = builtins.tryEval (builtins.deepSeq info info);
+    in
+    if found.success && lib.isDerivation pkg && result.success
+    then [ { name = lib.concatStringsSep "." path; value = result.value; } ]
+    else [ ];
+in
+builtins.listToAttrs (lib.concatMap describe (builtins.fromJSON attrs))
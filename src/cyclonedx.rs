@@ -0,0 +1,305 @@
+// Building CycloneDX BOMs from a derivation graph.
+
+use crate::cyclonedx_spec::{self, *};
+use crate::derivation::{DerivationGraph, Drv};
+use crate::identifiers::get_identifiers;
+use crate::nixpkgs::{Package, PackageIndex};
+use crate::DocumentInfo;
+use std::collections::HashMap;
+
+fn component(
+    component_type: ComponentType,
+    bom_ref: String,
+    graph: &DerivationGraph,
+    drv: &Drv,
+    package: Option<&Package>,
+) -> Component {
+    let hashes = graph
+        .get_checksums(drv)
+        .into_iter()
+        .flat_map(|(algorithm, content)| {
+            let alg = match algorithm.as_str() {
+                "md5" => "MD5",
+                "sha1" => "SHA-1",
+                "sha256" => "SHA-256",
+                "sha512" => "SHA-512",
+                _ => return None,
+            };
+            Some(Hash {
+                alg: alg.to_string(),
+                content,
+            })
+        })
+        .collect::<Vec<Hash>>();
+    let hashes = if hashes.is_empty() {
+        None
+    } else {
+        Some(hashes)
+    };
+    let mut external_references = graph
+        .get_download_location(drv)
+        .map(|v| ExternalReference {
+            reference_type: if v.is_vcs { "vcs" } else { "distribution" }.to_string(),
+            url: v.locator,
+        })
+        .into_iter()
+        .collect::<Vec<ExternalReference>>();
+    // CycloneDX has room for a single purl, the upstream one is what vulnerability databases know
+    let (purl, cpe) = match get_identifiers(drv, package) {
+        Some(v) => (Some(v.upstream_purl.unwrap_or(v.purl)), v.cpe),
+        None => (None, None),
+    };
+
+    let package = match package {
+        Some(package) => package,
+        None => {
+            let (name, version) = drv.name_and_version();
+            return Component {
+                component_type,
+                bom_ref: Some(bom_ref),
+                name,
+                version,
+                description: None,
+                hashes,
+                licenses: None,
+                cpe,
+                purl,
+                external_references: Some(external_references).filter(|v| !v.is_empty()),
+            };
+        }
+    };
+
+    let licenses = package.license_expression().map(|v| {
+        let license = if v.is_spdx_id() {
+            LicenseChoice::License(cyclonedx_spec::License {
+                id: Some(v.expression),
+                name: None,
+            })
+        } else {
+            match v.extracted.as_slice() {
+                // A single license SPDX doesn't know is better described by its name
+                [extracted] if !v.expression.contains(' ') => {
+                    LicenseChoice::License(cyclonedx_spec::License {
+                        id: None,
+                        name: Some(extracted.name.clone()),
+                    })
+                }
+                _ => LicenseChoice::Expression(v.expression),
+            }
+        };
+        vec![license]
+    });
+    if let Some(url) = package.homepage() {
+        external_references.push(ExternalReference {
+            reference_type: "website".to_string(),
+            url,
+        });
+    }
+
+    Component {
+        component_type,
+        bom_ref: Some(bom_ref),
+        name: package.pname.to_owned(),
+        version: Some(package.version.to_owned()),
+        description: package.meta.description.to_owned(),
+        hashes,
+        licenses,
+        cpe,
+        purl,
+        external_references: Some(external_references).filter(|v| !v.is_empty()),
+    }
+}
+
+impl Bom {
+    pub(crate) fn new(
+        info: &DocumentInfo,
+        graph: &DerivationGraph,
+        package_index: &PackageIndex,
+    ) -> Bom {
+        let root = graph.root.as_str();
+        let derivations = &graph.derivations;
+        let mut paths = derivations.keys().collect::<Vec<&String>>();
+        paths.sort();
+
+        let mut root_component = None;
+        let mut components = Vec::new();
+        let mut bom_refs = HashMap::new();
+        for path in paths {
+            let drv = &derivations[path];
+            let package = package_index.find(path, drv);
+
+            bom_refs.insert(path.clone(), path.clone());
+            if path == root {
+                root_component = Some(component(
+                    ComponentType::Application,
+                    path.clone(),
+                    graph,
+                    drv,
+                    package,
+                ));
+            } else {
+                components.push(component(
+                    ComponentType::Library,
+                    path.clone(),
+                    graph,
+                    drv,
+                    package,
+                ));
+            }
+        }
+
+        // The BOM always describes something, even if the root was filtered out
+        let root_component = root_component.unwrap_or_else(|| {
+            bom_refs.insert(root.to_string(), root.to_string());
+            Component {
+                component_type: ComponentType::Application,
+                bom_ref: Some(root.to_string()),
+                name: info.name.clone(),
+                version: None,
+                description: None,
+                hashes: None,
+                licenses: None,
+                cpe: None,
+                purl: None,
+                external_references: None,
+            }
+        });
+
+        let mut dependencies = Vec::new();
+        let mut paths = bom_refs.keys().collect::<Vec<&String>>();
+        paths.sort();
+        for path in paths {
+            let mut depends_on = graph
+                .dependencies(path)
+                .iter()
+                .flat_map(|input| bom_refs.get(input))
+                .cloned()
+                .collect::<Vec<String>>();
+            depends_on.sort();
+
+            dependencies.push(Dependency {
+                dependency_ref: bom_refs[path].clone(),
+                depends_on: Some(depends_on),
+            });
+        }
+
+        let metadata = Metadata {
+            timestamp: Some(info.created.clone()),
+            tools: Some(vec![Tool {
+                vendor: None,
+                name: Some("nixbom".to_string()),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }]),
+            authors: Some(
+                info.authors
+                    .iter()
+                    .map(|v| OrganizationalContact {
+                        name: Some(v.clone()),
+                    })
+                    .collect(),
+            ),
+            component: Some(root_component),
+        };
+
+        Bom {
+            bom_format: "CycloneDX".to_string(),
+            spec_version: info.cyclonedx_version.clone(),
+            version: 1,
+            metadata: Some(metadata),
+            components: Some(components),
+            dependencies: Some(dependencies),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    const CLOSURE: &str = include_str!("../tests/fixtures/hello-closure.json");
+    const PACKAGES: &str = include_str!("../tests/fixtures/hello-packages.json");
+    const HELLO: &str = "/nix/store/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10.drv";
+    const BASH: &str = "/nix/store/6cgi6gbpw7wxbwpf7kbq4xbxxs6s0yn1-bash-4.4-p23.drv";
+    const TARBALL: &str = "/nix/store/3x7dwzq014bblazs7kq20p9hyzz0qh8g-hello-2.10.tar.gz.drv";
+
+    fn bom() -> Value {
+        let graph = DerivationGraph::new(HELLO.to_string(), serde_json::from_str(CLOSURE).unwrap());
+        let package_index = PackageIndex::new(serde_json::from_str(PACKAGES).unwrap());
+        let info = DocumentInfo::new("hello", vec!["Jane Doe".to_string()]);
+
+        serde_json::to_value(Bom::new(&info, &graph, &package_index)).unwrap()
+    }
+
+    fn component<'a>(bom: &'a Value, bom_ref: &str) -> &'a Value {
+        std::iter::once(&bom["metadata"]["component"])
+            .chain(bom["components"].as_array().unwrap())
+            .find(|v| v["bom-ref"] == bom_ref)
+            .unwrap_or_else(|| panic!("{} missing", bom_ref))
+    }
+
+    #[test]
+    fn describes_the_root_in_the_metadata() {
+        let bom = bom();
+
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["specVersion"], "1.5");
+        assert_eq!(bom["metadata"]["component"]["bom-ref"], HELLO);
+        assert_eq!(bom["metadata"]["component"]["type"], "application");
+        assert_eq!(bom["metadata"]["authors"], json!([{"name": "Jane Doe"}]));
+    }
+
+    #[test]
+    fn single_spdx_licenses_are_ids() {
+        assert_eq!(
+            component(&bom(), HELLO)["licenses"],
+            json!([{"license": {"id": "GPL-3.0-or-later"}}])
+        );
+    }
+
+    #[test]
+    fn license_lists_are_an_expression() {
+        assert_eq!(
+            component(&bom(), BASH)["licenses"],
+            json!([{"expression": "GPL-3.0-or-later OR LicenseRef-bash-doc"}])
+        );
+    }
+
+    #[test]
+    fn fixed_output_hashes_and_download_locations() {
+        let bom = bom();
+        let tarball = component(&bom, TARBALL);
+
+        assert_eq!(tarball["type"], "library");
+        assert_eq!(tarball["licenses"], Value::Null);
+        assert_eq!(
+            tarball["hashes"],
+            json!([{
+                "alg": "SHA-256",
+                "content": "31e066137a962676e89f69d1b65382de95a7ef7d914b8cb956f41ea72e0f516b"
+            }])
+        );
+        assert_eq!(
+            tarball["externalReferences"],
+            json!([{
+                "type": "distribution",
+                "url": "https://ftpmirror.gnu.org/hello/hello-2.10.tar.gz"
+            }])
+        );
+    }
+
+    #[test]
+    fn dependencies_follow_input_derivations() {
+        let bom = bom();
+
+        // stdenv isn't part of the fixture closure, so it's left out
+        assert_eq!(
+            bom["dependencies"],
+            json!([
+                {"ref": HELLO, "dependsOn": [TARBALL, BASH]},
+                {"ref": TARBALL, "dependsOn": []},
+                {"ref": BASH, "dependsOn": []},
+            ])
+        );
+    }
+}
@@ -0,0 +1,155 @@
+// Subset of the CycloneDX 1.4/1.5 object model that nixbom emits. Field order follows the
+// CycloneDX XML schema, which is also the order the XML writer walks them in.
+
+extern crate serde_derive;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bom {
+    /// Specifies the format of the BOM. Always `CycloneDX`.
+    #[serde(rename = "bomFormat")]
+    pub bom_format: String,
+    /// The version of the CycloneDX specification a BOM is written to.
+    #[serde(rename = "specVersion")]
+    pub spec_version: String,
+    /// The version allows component publishers/authors to make changes to existing BOMs to
+    /// update various aspects of the document such as description or licenses.
+    pub version: u32,
+    /// Provides additional information about a BOM.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<Component>>,
+    /// Provides the ability to document dependency relationships.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<Vec<Dependency>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    /// The date and time (timestamp) when the BOM was created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// The tool(s) used in the creation of the BOM.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    /// The person(s) who created the BOM.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authors: Option<Vec<OrganizationalContact>>,
+    /// The component that the BOM describes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<Component>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tool {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrganizationalContact {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Component {
+    /// Specifies the type of component.
+    #[serde(rename = "type")]
+    pub component_type: ComponentType,
+    /// An optional identifier which can be used to reference the component elsewhere in the BOM.
+    #[serde(rename = "bom-ref", skip_serializing_if = "Option::is_none")]
+    pub bom_ref: Option<String>,
+    /// The name of the component.
+    pub name: String,
+    /// The component version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Specifies a description for the component.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hashes: Option<Vec<Hash>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub licenses: Option<Vec<LicenseChoice>>,
+    /// Specifies a well-formed CPE name that conforms to the CPE 2.2 or 2.3 specification.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpe: Option<String>,
+    /// Package-URL (purl), see https://github.com/package-url/purl-spec.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purl: Option<String>,
+    /// External references provide a way to document systems, sites, and information that may
+    /// be relevant but which are not included with the BOM.
+    #[serde(rename = "externalReferences", skip_serializing_if = "Option::is_none")]
+    pub external_references: Option<Vec<ExternalReference>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ComponentType {
+    #[serde(rename = "application")]
+    Application,
+    #[serde(rename = "library")]
+    Library,
+}
+
+impl ComponentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ComponentType::Application => "application",
+            ComponentType::Library => "library",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Hash {
+    /// The algorithm that generated the hash value, e.g. `SHA-256`.
+    pub alg: String,
+    /// The value of the hash.
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum LicenseChoice {
+    /// A single license, either by SPDX license id or by name.
+    #[serde(rename = "license")]
+    License(License),
+    /// A valid SPDX license expression.
+    #[serde(rename = "expression")]
+    Expression(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct License {
+    /// A valid SPDX license ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// If SPDX does not define the license used, this field may be used to provide the license
+    /// name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExternalReference {
+    /// Specifies the type of external reference.
+    #[serde(rename = "type")]
+    pub reference_type: String,
+    /// The URI (URL or URN) to the external reference.
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Dependency {
+    /// References a component by the component's bom-ref attribute.
+    #[serde(rename = "ref")]
+    pub dependency_ref: String,
+    /// The bom-ref identifiers of the components that are dependencies of this dependency
+    /// object.
+    #[serde(rename = "dependsOn", skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Vec<String>>,
+}
@@ -0,0 +1,338 @@
+use crate::cyclonedx_spec::*;
+
+/// Minimal indenting XML writer, enough for the flat element structure of a CycloneDX BOM.
+struct XmlWriter {
+    out: String,
+    depth: usize,
+}
+
+/// Whether XML 1.0 allows the character at all, even as a character reference. Descriptions
+/// from nixpkgs occasionally carry control characters like ANSI escapes.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// Escapes markup and drops the characters XML 1.0 can't represent.
+fn escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| is_xml_char(*c))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl XmlWriter {
+    fn new() -> XmlWriter {
+        XmlWriter {
+            out: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"),
+            depth: 0,
+        }
+    }
+
+    /// Writes `<tag attributes…` and leaves the tag open for the caller to close.
+    fn open_tag(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push('<');
+        self.out.push_str(tag);
+        for (name, value) in attributes {
+            self.out
+                .push_str(&format!(" {}=\"{}\"", name, escape(value)));
+        }
+    }
+
+    fn start(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.open_tag(tag, attributes);
+        self.out.push_str(">\n");
+        self.depth += 1;
+    }
+
+    fn empty(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.open_tag(tag, attributes);
+        self.out.push_str("/>\n");
+    }
+
+    fn end(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push_str(&format!("</{}>\n", tag));
+    }
+
+    fn text(&mut self, tag: &str, text: &str) {
+        self.text_with_attributes(tag, &[], text);
+    }
+
+    fn text_with_attributes(&mut self, tag: &str, attributes: &[(&str, &str)], text: &str) {
+        self.open_tag(tag, attributes);
+        self.out.push_str(&format!(">{}</{}>\n", escape(text), tag));
+    }
+
+    fn optional_text(&mut self, tag: &str, text: &Option<String>) {
+        if let Some(text) = text {
+            self.text(tag, text);
+        }
+    }
+}
+
+fn write_component(w: &mut XmlWriter, component: &Component) {
+    let mut attributes = vec![("type", component.component_type.as_str())];
+    if let Some(bom_ref) = &component.bom_ref {
+        attributes.push(("bom-ref", bom_ref));
+    }
+
+    w.start("component", &attributes);
+    w.text("name", &component.name);
+    w.optional_text("version", &component.version);
+    w.optional_text("description", &component.description);
+    if let Some(hashes) = &component.hashes {
+        w.start("hashes", &[]);
+        for hash in hashes {
+            w.text_with_attributes("hash", &[("alg", &hash.alg)], &hash.content);
+        }
+        w.end("hashes");
+    }
+    if let Some(licenses) = &component.licenses {
+        w.start("licenses", &[]);
+        for license in licenses {
+            match license {
+                LicenseChoice::License(l) => {
+                    w.start("license", &[]);
+                    w.optional_text("id", &l.id);
+                    w.optional_text("name", &l.name);
+                    w.end("license");
+                }
+                LicenseChoice::Expression(e) => w.text("expression", e),
+            }
+        }
+        w.end("licenses");
+    }
+    w.optional_text("cpe", &component.cpe);
+    w.optional_text("purl", &component.purl);
+    if let Some(references) = &component.external_references {
+        w.start("externalReferences", &[]);
+        for reference in references {
+            w.start("reference", &[("type", &reference.reference_type)]);
+            w.text("url", &reference.url);
+            w.end("reference");
+        }
+        w.end("externalReferences");
+    }
+    w.end("component");
+}
+
+fn write_metadata(w: &mut XmlWriter, metadata: &Metadata) {
+    w.start("metadata", &[]);
+    w.optional_text("timestamp", &metadata.timestamp);
+    if let Some(tools) = &metadata.tools {
+        w.start("tools", &[]);
+        for tool in tools {
+            w.start("tool", &[]);
+            w.optional_text("vendor", &tool.vendor);
+            w.optional_text("name", &tool.name);
+            w.optional_text("version", &tool.version);
+            w.end("tool");
+        }
+        w.end("tools");
+    }
+    if let Some(authors) = &metadata.authors {
+        w.start("authors", &[]);
+        for author in authors {
+            w.start("author", &[]);
+            w.optional_text("name", &author.name);
+            w.end("author");
+        }
+        w.end("authors");
+    }
+    if let Some(component) = &metadata.component {
+        write_component(w, component);
+    }
+    w.end("metadata");
+}
+
+impl Bom {
+    /// Renders the BOM using the CycloneDX XML schema of its `spec_version`.
+    pub fn to_xml(&self) -> String {
+        let mut w = XmlWriter::new();
+        let namespace = format!("http://cyclonedx.org/schema/bom/{}", self.spec_version);
+        let version = self.version.to_string();
+
+        w.start("bom", &[("xmlns", &namespace), ("version", &version)]);
+        if let Some(metadata) = &self.metadata {
+            write_metadata(&mut w, metadata);
+        }
+        if let Some(components) = &self.components {
+            w.start("components", &[]);
+            for component in components {
+                write_component(&mut w, component);
+            }
+            w.end("components");
+        }
+        if let Some(dependencies) = &self.dependencies {
+            w.start("dependencies", &[]);
+            for dependency in dependencies {
+                let attributes = [("ref", dependency.dependency_ref.as_str())];
+                match &dependency.depends_on {
+                    Some(depends_on) if !depends_on.is_empty() => {
+                        w.start("dependency", &attributes);
+                        for v in depends_on {
+                            w.empty("dependency", &[("ref", v)]);
+                        }
+                        w.end("dependency");
+                    }
+                    _ => w.empty("dependency", &attributes),
+                }
+            }
+            w.end("dependencies");
+        }
+        w.end("bom");
+
+        w.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component() -> Component {
+        Component {
+            component_type: ComponentType::Library,
+            bom_ref: Some("/nix/store/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10.drv".to_string()),
+            name: "hello".to_string(),
+            version: Some("2.10".to_string()),
+            description: Some("A program that produces a familiar, friendly greeting".to_string()),
+            hashes: Some(vec![Hash {
+                alg: "SHA-256".to_string(),
+                content: "31e066137a962676e89f69d1b65382de95a7ef7d914b8cb956f41ea72e0f516b"
+                    .to_string(),
+            }]),
+            licenses: Some(vec![LicenseChoice::License(License {
+                id: Some("GPL-3.0-or-later".to_string()),
+                name: None,
+            })]),
+            cpe: Some("cpe:2.3:a:gnu:hello:2.10:*:*:*:*:*:*:*".to_string()),
+            purl: Some("pkg:nix/hello@2.10".to_string()),
+            external_references: Some(vec![ExternalReference {
+                reference_type: "website".to_string(),
+                url: "https://www.gnu.org/software/hello/".to_string(),
+            }]),
+        }
+    }
+
+    fn bom(components: Vec<Component>, dependencies: Option<Vec<Dependency>>) -> Bom {
+        Bom {
+            bom_format: "CycloneDX".to_string(),
+            spec_version: "1.5".to_string(),
+            version: 1,
+            metadata: None,
+            components: Some(components),
+            dependencies,
+        }
+    }
+
+    #[test]
+    fn escapes_markup() {
+        let mut component = component();
+        component.name = "a&b<c>".to_string();
+        component.description = Some("\"double\" 'single'".to_string());
+        let xml = bom(vec![component], None).to_xml();
+
+        assert!(xml.contains("<name>a&amp;b&lt;c&gt;</name>"), "{}", xml);
+        assert!(
+            xml.contains("<description>&quot;double&quot; &apos;single&apos;</description>"),
+            "{}",
+            xml
+        );
+    }
+
+    #[test]
+    fn drops_characters_xml_cannot_represent() {
+        let mut component = component();
+        component.description = Some("\u{1b}[1mbold\u{1b}[0m\u{0}\ttab\u{FFFE}".to_string());
+        let xml = bom(vec![component], None).to_xml();
+
+        assert!(
+            xml.contains("<description>[1mbold[0m\ttab</description>"),
+            "{}",
+            xml
+        );
+        assert!(!xml.chars().any(|c| !is_xml_char(c)));
+    }
+
+    #[test]
+    fn writes_component_elements_in_schema_order() {
+        let xml = bom(vec![component()], None).to_xml();
+        let positions = [
+            "<component type=\"library\" bom-ref=",
+            "<name>",
+            "<version>",
+            "<description>",
+            "<hashes>",
+            "<licenses>",
+            "<cpe>",
+            "<purl>",
+            "<externalReferences>",
+            "</component>",
+        ]
+        .iter()
+        .map(|tag| xml.find(tag).unwrap_or_else(|| panic!("{} missing", tag)))
+        .collect::<Vec<usize>>();
+
+        assert!(positions.windows(2).all(|v| v[0] < v[1]), "{}", xml);
+        assert!(xml.contains("<hash alg=\"SHA-256\">31e06613"));
+    }
+
+    #[test]
+    fn writes_license_ids_and_expressions() {
+        let mut expression = component();
+        expression.licenses = Some(vec![LicenseChoice::Expression(
+            "GPL-3.0-or-later AND LicenseRef-bash".to_string(),
+        )]);
+        let mut named = component();
+        named.licenses = Some(vec![LicenseChoice::License(License {
+            id: None,
+            name: Some("Bash license".to_string()),
+        })]);
+        let xml = bom(vec![component(), expression, named], None).to_xml();
+
+        assert!(xml.contains(
+            "<licenses>\n        <license>\n          <id>GPL-3.0-or-later</id>\n        </license>\n      </licenses>"
+        ));
+        assert!(xml.contains(
+            "<licenses>\n        <expression>GPL-3.0-or-later AND LicenseRef-bash</expression>\n      </licenses>"
+        ));
+        assert!(xml.contains("<license>\n          <name>Bash license</name>\n        </license>"));
+    }
+
+    #[test]
+    fn writes_the_dependency_tree() {
+        let dependencies = vec![
+            Dependency {
+                dependency_ref: "hello".to_string(),
+                depends_on: Some(vec!["bash".to_string(), "hello.tar.gz".to_string()]),
+            },
+            Dependency {
+                dependency_ref: "bash".to_string(),
+                depends_on: Some(Vec::new()),
+            },
+        ];
+        let xml = bom(Vec::new(), Some(dependencies)).to_xml();
+
+        assert!(
+            xml.contains(
+                "  <dependencies>
+    <dependency ref=\"hello\">
+      <dependency ref=\"bash\"/>
+      <dependency ref=\"hello.tar.gz\"/>
+    </dependency>
+    <dependency ref=\"bash\"/>
+  </dependencies>
+"
+            ),
+            "{}",
+            xml
+        );
+    }
+}
@@ -0,0 +1,567 @@
+// Loading derivations and their closures, either through `nix show-derivation` or by parsing
+// `.drv` files directly.
+
+use crate::error::{command_output, Error};
+use crate::input::Input;
+use crate::{aterm, fetcher, nix_hash};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Out {
+    pub path: String,
+    #[serde(rename = "hashAlgo")]
+    pub hash_algo: Option<String>,
+    pub hash: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Drv {
+    pub outputs: HashMap<String, Out>,
+    #[serde(rename = "inputSrcs")]
+    pub input_srcs: Vec<String>,
+    #[serde(rename = "inputDrvs")]
+    pub input_drvs: HashMap<String, Vec<String>>,
+    pub system: String,
+    pub builder: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Drv {
+    /// Returns the name and version of the derivation, preferring the explicit `pname` and
+    /// `version` attributes and otherwise splitting `name` like `builtins.parseDrvName` does: the
+    /// version starts after the first dash that isn't followed by a letter.
+    pub fn name_and_version(&self) -> (String, Option<String>) {
+        if let (Some(pname), Some(version)) = (self.env.get("pname"), self.env.get("version")) {
+            return (pname.clone(), Some(version.clone()));
+        }
+
+        let name = self.env.get("name").map(String::as_str).unwrap_or_default();
+        let split = name.char_indices().find(|(i, c)| {
+            *c == '-'
+                && name[i + 1..]
+                    .chars()
+                    .next()
+                    .is_some_and(|v| !v.is_alphabetic())
+        });
+
+        match split {
+            Some((i, _)) => (name[..i].to_string(), Some(name[i + 1..].to_string())),
+            None => (name.to_string(), None),
+        }
+    }
+
+    /// Store path of the main output, `out` if the derivation has one.
+    pub fn out_path(&self) -> Option<&str> {
+        self.outputs
+            .get("out")
+            .or_else(|| {
+                let mut outputs = self.outputs.iter().collect::<Vec<(&String, &Out)>>();
+                outputs.sort_by_key(|(name, _)| *name);
+                outputs.first().map(|(_, v)| *v)
+            })
+            .map(|v| v.path.as_str())
+    }
+}
+
+/// Which dependencies a derivation graph follows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Closure {
+    /// Everything needed to build the root, following `inputDrvs`.
+    Build,
+    /// Only what the realised root references at runtime, following the store path references
+    /// recorded in the Nix database.
+    Runtime,
+}
+
+/// How a derivation uses one of its input derivations at build time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputKind {
+    /// Runs during the build, e.g. a compiler from `nativeBuildInputs`.
+    BuildTool,
+    /// Is built against, from `buildInputs` or `propagatedBuildInputs`.
+    Dependency,
+    /// The source the derivation is built from, its `src` or one of its `srcs`.
+    Source,
+    /// A fetched patch from `patches`.
+    Patch,
+    /// Anything not referenced by one of the attributes above, e.g. `stdenv` or the builder.
+    Other,
+}
+
+/// Environment attributes telling how an input is used, in order of precedence.
+const INPUT_KINDS: &[(&str, InputKind)] = &[
+    ("src", InputKind::Source),
+    ("srcs", InputKind::Source),
+    ("patches", InputKind::Patch),
+    ("nativeBuildInputs", InputKind::BuildTool),
+    ("propagatedNativeBuildInputs", InputKind::BuildTool),
+    ("depsBuildBuild", InputKind::BuildTool),
+    ("buildInputs", InputKind::Dependency),
+    ("propagatedBuildInputs", InputKind::Dependency),
+];
+
+/// The closure of derivations an SBOM is generated for.
+pub struct DerivationGraph {
+    pub root: String,
+    pub derivations: HashMap<String, Drv>,
+    pub closure: Closure,
+    /// Derivations each derivation depends on, keyed by derivation store path.
+    pub(crate) dependencies: HashMap<String, Vec<String>>,
+    /// NAR hashes of realised outputs, as lowercase hex sha256 keyed by output store path.
+    pub(crate) nar_hashes: HashMap<String, String>,
+    /// Derivation store paths keyed by the store paths of their outputs.
+    pub(crate) out_paths: HashMap<String, String>,
+}
+
+impl DerivationGraph {
+    pub fn new(root: String, derivations: HashMap<String, Drv>) -> DerivationGraph {
+        let out_paths = derivations
+            .iter()
+            .flat_map(|(drv_path, drv)| {
+                drv.outputs
+                    .values()
+                    .map(move |v| (v.path.clone(), drv_path.clone()))
+            })
+            .collect();
+        let dependencies = derivations
+            .iter()
+            .map(|(drv_path, drv)| {
+                let mut inputs = drv.input_drvs.keys().cloned().collect::<Vec<String>>();
+                inputs.sort();
+                (drv_path.clone(), inputs)
+            })
+            .collect();
+
+        DerivationGraph {
+            root,
+            derivations,
+            closure: Closure::Build,
+            dependencies,
+            nar_hashes: HashMap::new(),
+            out_paths,
+        }
+    }
+
+    /// Returns the derivations a derivation depends on, sorted.
+    pub fn dependencies(&self, drv_path: &str) -> &[String] {
+        self.dependencies
+            .get(drv_path)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the derivation that produces the given output store path.
+    pub(crate) fn get_deriver(&self, out_path: &str) -> Option<&Drv> {
+        self.out_paths
+            .get(out_path)
+            .and_then(|v| self.derivations.get(v))
+    }
+
+    /// Classifies an input derivation of `drv` by the environment attribute referencing one of
+    /// its outputs.
+    pub fn input_kind(&self, drv: &Drv, input: &str) -> InputKind {
+        INPUT_KINDS
+            .iter()
+            .find(|(attribute, _)| {
+                drv.env.get(*attribute).is_some_and(|v| {
+                    v.split_whitespace()
+                        .any(|path| self.out_paths.get(path).is_some_and(|v| v == input))
+                })
+            })
+            .map_or(InputKind::Other, |(_, kind)| *kind)
+    }
+
+    /// Returns the patches in `patches` that are plain files in the store rather than outputs
+    /// of a derivation, like the patches nixpkgs carries next to its expressions.
+    pub fn local_patches<'a>(&self, drv: &'a Drv) -> Vec<&'a str> {
+        drv.env
+            .get("patches")
+            .map(|v| {
+                v.split_whitespace()
+                    .filter(|path| drv.input_srcs.iter().any(|v| v == path))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns where a derivation was downloaded from: fetchers know that themselves, anything
+    /// else inherits the location of the fetcher its `src` (or first of `srcs`) came from.
+    pub(crate) fn get_download_location(&self, drv: &Drv) -> Option<fetcher::DownloadLocation> {
+        fetcher::download_location(drv).or_else(|| {
+            let src = drv
+                .env
+                .get("src")
+                .or_else(|| drv.env.get("srcs"))
+                .and_then(|v| v.split_whitespace().next())?;
+
+            fetcher::download_location(self.get_deriver(src)?)
+        })
+    }
+
+    /// Returns `(algorithm, hex digest)` pairs for a derivation. Fixed-output derivations carry
+    /// the hash of their output, flat or recursive (NAR), in the derivation itself. Anything else
+    /// only has a checksum if its NAR hash was looked up.
+    pub fn get_checksums(&self, drv: &Drv) -> Vec<(String, String)> {
+        let mut checksums = drv
+            .outputs
+            .values()
+            .flat_map(|out| {
+                let hash_algo = out.hash_algo.as_ref()?;
+                let algorithm = hash_algo.rsplit(':').next().unwrap_or(hash_algo);
+                let hex = nix_hash::hash_to_hex(algorithm, out.hash.as_ref()?)?;
+                Some((algorithm.to_string(), hex))
+            })
+            .collect::<Vec<(String, String)>>();
+
+        if checksums.is_empty() {
+            if let Some(hex) = drv.out_path().and_then(|v| self.nar_hashes.get(v)) {
+                checksums.push(("sha256".to_string(), hex.clone()));
+            }
+        }
+        checksums.sort();
+
+        checksums
+    }
+
+    /// Looks up the NAR hash of every realised output that isn't already covered by a
+    /// fixed-output hash, and of every local patch.
+    pub fn add_nar_hashes(&mut self) -> Result<(), Error> {
+        let out_paths = self
+            .derivations
+            .values()
+            .filter(|drv| drv.outputs.values().all(|v| v.hash.is_none()))
+            .flat_map(|drv| drv.out_path())
+            .chain(
+                self.derivations
+                    .values()
+                    .flat_map(|drv| self.local_patches(drv)),
+            )
+            .filter(|v| Path::new(v).exists())
+            .map(|v| v.to_string())
+            .collect::<HashSet<String>>();
+
+        for out_path in out_paths {
+            if let Some(hex) = get_nar_hash(&out_path)? {
+                self.nar_hashes.insert(out_path, hex);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn get_nar_hash(path: &str) -> Result<Option<String>, Error> {
+    let output = command_output(
+        Command::new("nix-store")
+            .arg("--query")
+            .arg("--hash")
+            .arg(path),
+    )?;
+    let hash = String::from_utf8_lossy(&output);
+
+    Ok(nix_hash::hash_to_hex("sha256", hash.trim()))
+}
+
+pub(crate) trait Derivation {
+    fn get_inner_drv(&self) -> Vec<Drv>;
+    fn get_input_derivations(
+        &self,
+        depth: Option<usize>,
+        source: &DerivationSource,
+    ) -> Result<HashMap<String, Drv>, Error>;
+}
+
+/// Where derivations are loaded from.
+pub enum DerivationSource {
+    /// Ask `nix show-derivation`, which needs the `nix` binary and the `nix-command` feature.
+    Nix,
+    /// Parse the `.drv` files directly, resolving store paths against the given root
+    /// directory. This also works for copied store snapshots.
+    Store(PathBuf),
+}
+
+impl DerivationSource {
+    /// Whether the `.drv` file is there to be loaded, it may have been garbage collected or
+    /// never been copied along with its outputs.
+    fn has_derivation(&self, path: &str) -> bool {
+        match self {
+            DerivationSource::Nix => Path::new(path).exists(),
+            DerivationSource::Store(root) => root.join(path.trim_start_matches('/')).exists(),
+        }
+    }
+
+    pub(crate) fn get_derivation(&self, path: &str) -> Result<HashMap<String, Drv>, Error> {
+        match self {
+            DerivationSource::Nix => show_derivation(&Input::DrvPath(path.to_string())),
+            DerivationSource::Store(root) => {
+                let file = root.join(path.trim_start_matches('/'));
+                let contents = std::fs::read_to_string(&file)
+                    .map_err(|e| Error::io(format!("reading {}", file.display()), e))?;
+                let drv =
+                    aterm::parse_derivation(&contents).map_err(|e| Error::InvalidDerivation {
+                        drv_path: file.display().to_string(),
+                        position: e.position,
+                        message: e.message,
+                    })?;
+                let mut drvs = HashMap::new();
+                drvs.insert(path.to_string(), drv);
+                check_derivations(&drvs)?;
+                Ok(drvs)
+            }
+        }
+    }
+}
+
+impl Derivation for HashMap<String, Drv> {
+    fn get_inner_drv(&self) -> Vec<Drv> {
+        self.values().cloned().collect()
+    }
+
+    /// Returns the transitive closure of input derivations keyed by their store path, so the
+    /// edges in `input_drvs` can still be resolved once the derivations are merged into a single
+    /// map. The graph is walked breadth first and every store path is only loaded once, which
+    /// also keeps cycles from looping forever. A `depth` of `Some(1)` only returns the direct
+    /// inputs, `None` walks the whole closure.
+    fn get_input_derivations(
+        &self,
+        depth: Option<usize>,
+        source: &DerivationSource,
+    ) -> Result<HashMap<String, Drv>, Error> {
+        let mut visited = self.keys().cloned().collect::<HashSet<String>>();
+        let mut derivations = HashMap::new();
+        let mut frontier = self
+            .get_inner_drv()
+            .into_iter()
+            .flat_map(|v| v.input_drvs.into_keys())
+            .collect::<Vec<String>>();
+        let mut level = 1;
+
+        while !frontier.is_empty() && depth.is_none_or(|d| level <= d) {
+            let mut next = Vec::new();
+
+            for path in frontier {
+                if !visited.insert(path.clone()) {
+                    continue;
+                }
+
+                let drvs = source.get_derivation(&path)?;
+                for (drv_path, drv) in drvs {
+                    next.extend(
+                        drv.input_drvs
+                            .keys()
+                            .filter(|v| !visited.contains(*v))
+                            .cloned(),
+                    );
+                    visited.insert(drv_path.clone());
+                    derivations.insert(drv_path, drv);
+                }
+            }
+
+            frontier = next;
+            level += 1;
+        }
+
+        Ok(derivations)
+    }
+}
+
+/// Every derivation has a `name`, anything without one isn't what we think it is.
+fn check_derivations(drvs: &HashMap<String, Drv>) -> Result<(), Error> {
+    match drvs.iter().find(|(_, drv)| !drv.env.contains_key("name")) {
+        Some((drv_path, _)) => Err(Error::MissingEnvAttribute {
+            drv_path: drv_path.clone(),
+            attribute: "name".to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Runs `nix show-derivation` on anything but an output path, which has to be resolved with
+/// `get_deriver` first.
+pub(crate) fn show_derivation(input: &Input) -> Result<HashMap<String, Drv>, Error> {
+    let mut command = Command::new("nix");
+    match input {
+        Input::Flake(v) => command
+            .arg("--extra-experimental-features")
+            .arg("nix-command flakes")
+            .arg("show-derivation")
+            .arg(v),
+        Input::Expression(v) => command.arg("show-derivation").arg("-f").arg(v),
+        Input::DrvPath(v) | Input::OutputPath(v) => command.arg("show-derivation").arg(v),
+    };
+
+    let output = command_output(&mut command)?;
+    let drvs: HashMap<String, Drv> = serde_json::from_slice(&output)
+        .map_err(|e| Error::json(format!("`nix show-derivation` for {}", input.as_str()), e))?;
+    check_derivations(&drvs)?;
+
+    Ok(drvs)
+}
+
+/// Looks up the derivation that produced an output store path in the Nix database.
+pub(crate) fn get_deriver(out_path: &str) -> Result<String, Error> {
+    query_deriver(out_path)?.ok_or_else(|| {
+        Error::InvalidInput(format!(
+            "the deriver of {} is unknown to the Nix database",
+            out_path
+        ))
+    })
+}
+
+fn query_deriver(out_path: &str) -> Result<Option<String>, Error> {
+    let output = command_output(
+        Command::new("nix-store")
+            .arg("--query")
+            .arg("--deriver")
+            .arg(out_path),
+    )?;
+    let deriver = String::from_utf8_lossy(&output).trim().to_string();
+
+    if deriver.is_empty() || deriver == "unknown-deriver" {
+        Ok(None)
+    } else {
+        Ok(Some(deriver))
+    }
+}
+
+/// Returns the store paths a realised store path references, other than itself.
+fn query_references(path: &str) -> Result<Vec<String>, Error> {
+    let output = command_output(
+        Command::new("nix-store")
+            .arg("--query")
+            .arg("--references")
+            .arg(path),
+    )?;
+
+    Ok(String::from_utf8_lossy(&output)
+        .lines()
+        .map(str::trim)
+        .filter(|v| !v.is_empty() && *v != path)
+        .map(|v| v.to_string())
+        .collect())
+}
+
+/// Stands in for a derivation that isn't available, describing an output by its store path.
+pub(crate) fn store_path_drv(path: &str) -> Drv {
+    let name = store_path_name(path);
+    let name = name.split_once('-').map_or(name, |(_, v)| v);
+
+    let mut outputs = HashMap::new();
+    outputs.insert(
+        "out".to_string(),
+        Out {
+            path: path.to_string(),
+            hash_algo: None,
+            hash: None,
+        },
+    );
+    let mut env = HashMap::new();
+    env.insert("name".to_string(), name.to_string());
+    env.insert("out".to_string(), path.to_string());
+
+    Drv {
+        outputs,
+        input_srcs: Vec::new(),
+        input_drvs: HashMap::new(),
+        system: String::new(),
+        builder: String::new(),
+        args: Vec::new(),
+        env,
+        extra: HashMap::new(),
+    }
+}
+
+/// Loads the runtime closure of realised store paths by walking their references in the Nix
+/// database breadth first, up to `depth` levels. Each store path is described by the derivation
+/// that produced it, outputs of the same derivation collapse into one node. Paths whose
+/// derivation is unknown or unavailable are described by their store path alone.
+pub(crate) fn get_runtime_closure(
+    out_paths: &[String],
+    depth: Option<usize>,
+    source: &DerivationSource,
+) -> Result<DerivationGraph, Error> {
+    let mut visited = out_paths.iter().cloned().collect::<HashSet<String>>();
+    let mut references = HashMap::new();
+    let mut frontier = out_paths.to_vec();
+    let mut level = 0;
+
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+
+        for path in frontier {
+            let refs = if depth.is_none_or(|d| level < d) {
+                query_references(&path)?
+            } else {
+                Vec::new()
+            };
+            next.extend(
+                refs.iter()
+                    .filter(|v| visited.insert(v.to_string()))
+                    .cloned(),
+            );
+            references.insert(path, refs);
+        }
+
+        frontier = next;
+        level += 1;
+    }
+
+    // Sorted so that the node a derivation-less path is keyed by doesn't depend on hash order
+    let mut paths = references.keys().cloned().collect::<Vec<String>>();
+    paths.sort();
+
+    let mut nodes = HashMap::new();
+    let mut derivations = HashMap::new();
+    for path in paths {
+        let deriver = query_deriver(&path)?.filter(|v| source.has_derivation(v));
+        let node = match deriver {
+            Some(deriver) => {
+                if !derivations.contains_key(&deriver) {
+                    derivations.extend(source.get_derivation(&deriver)?);
+                }
+                deriver
+            }
+            None => {
+                derivations.insert(path.clone(), store_path_drv(&path));
+                path.clone()
+            }
+        };
+        nodes.insert(path, node);
+    }
+
+    let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
+    for (path, refs) in &references {
+        let node = &nodes[path];
+        let inputs = dependencies.entry(node.clone()).or_default();
+        inputs.extend(refs.iter().map(|v| nodes[v].clone()).filter(|v| v != node));
+    }
+    for inputs in dependencies.values_mut() {
+        inputs.sort();
+        inputs.dedup();
+    }
+
+    let root = nodes[&out_paths[0]].clone();
+    let mut graph = DerivationGraph::new(root, derivations);
+    graph.closure = Closure::Runtime;
+    graph.dependencies = dependencies;
+
+    Ok(graph)
+}
+
+/// Returns the `<hash>-<name>` part of a store path, without the `.drv` extension.
+pub(crate) fn store_path_name(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".drv").unwrap_or(name)
+}
+
+/// Returns the hash part of a store path, which is stable for as long as the derivation doesn't
+/// change.
+pub(crate) fn store_path_hash(path: &str) -> &str {
+    let name = store_path_name(path);
+    name.split('-').next().unwrap_or(name)
+}
@@ -0,0 +1,466 @@
+// Comparing two SBOMs, generated on the fly or read back from SPDX and CycloneDX JSON documents
+// nixbom wrote earlier.
+
+use crate::cyclonedx_spec::{Bom, LicenseChoice};
+use crate::error::Error;
+use crate::spdx_spec::SpdxSchema;
+use crate::spdx_tag_value::enum_name;
+use crate::version::compare_versions;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+const NOASSERTION: &str = "NOASSERTION";
+
+/// The parts of a package a diff looks at.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Component {
+    pub name: String,
+    pub version: Option<String>,
+    pub license: Option<String>,
+    /// `ALGORITHM:hex` pairs like `SHA256:…`, sorted. Algorithm names are normalised so that
+    /// SPDX and CycloneDX documents compare equal.
+    pub checksums: Vec<String>,
+}
+
+impl Component {
+    fn label(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{} {}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+fn checksum(algorithm: &str, value: &str) -> String {
+    format!(
+        "{}:{}",
+        algorithm.replace('-', "").to_uppercase(),
+        value.to_lowercase()
+    )
+}
+
+/// Extracts the packages of an SPDX document.
+pub fn spdx_components(spdx: &SpdxSchema) -> Vec<Component> {
+    spdx.document
+        .packages
+        .iter()
+        .flatten()
+        .map(|package| {
+            let license = [&package.license_concluded, &package.license_declared]
+                .iter()
+                .flat_map(|v| v.as_ref())
+                .find(|v| v.as_str() != NOASSERTION)
+                .cloned();
+            let mut checksums = package
+                .checksums
+                .iter()
+                .flatten()
+                .flat_map(|v| {
+                    Some(checksum(
+                        &enum_name(v.algorithm.as_ref()?),
+                        v.checksum_value.as_ref()?,
+                    ))
+                })
+                .collect::<Vec<String>>();
+            checksums.sort();
+
+            Component {
+                name: package.name.clone().unwrap_or_default(),
+                version: package.version_info.clone(),
+                license,
+                checksums,
+            }
+        })
+        .collect()
+}
+
+/// Extracts the components of a CycloneDX BOM, including the one it describes.
+pub fn cyclonedx_components(bom: &Bom) -> Vec<Component> {
+    bom.metadata
+        .iter()
+        .flat_map(|v| &v.component)
+        .chain(bom.components.iter().flatten())
+        .map(|component| {
+            let licenses = component
+                .licenses
+                .iter()
+                .flatten()
+                .flat_map(|v| match v {
+                    LicenseChoice::License(license) => license.id.clone().or(license.name.clone()),
+                    LicenseChoice::Expression(expression) => Some(expression.clone()),
+                })
+                .collect::<Vec<String>>();
+            let mut checksums = component
+                .hashes
+                .iter()
+                .flatten()
+                .map(|v| checksum(&v.alg, &v.content))
+                .collect::<Vec<String>>();
+            checksums.sort();
+
+            Component {
+                name: component.name.clone(),
+                version: component.version.clone(),
+                license: Some(licenses.join(" AND ")).filter(|v| !v.is_empty()),
+                checksums,
+            }
+        })
+        .collect()
+}
+
+/// Reads the packages of an SPDX or CycloneDX JSON document.
+pub fn read_components(path: &Path) -> Result<Vec<Component>, Error> {
+    let contents =
+        fs::read(path).map_err(|e| Error::io(format!("reading {}", path.display()), e))?;
+    let document: serde_json::Value = serde_json::from_slice(&contents)
+        .map_err(|e| Error::json(path.display().to_string(), e))?;
+
+    if document.get("spdxVersion").is_some() {
+        let spdx = serde_json::from_value::<SpdxSchema>(document)
+            .map_err(|e| Error::json(path.display().to_string(), e))?;
+        Ok(spdx_components(&spdx))
+    } else if document.get("bomFormat").is_some() {
+        let bom = serde_json::from_value::<Bom>(document)
+            .map_err(|e| Error::json(path.display().to_string(), e))?;
+        Ok(cyclonedx_components(&bom))
+    } else {
+        Err(Error::InvalidInput(format!(
+            "{} is neither an SPDX nor a CycloneDX JSON document",
+            path.display()
+        )))
+    }
+}
+
+/// A package whose version changed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VersionChange {
+    pub name: String,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+}
+
+/// A value that changed for a package found in both SBOMs, e.g. its license.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change<T> {
+    pub name: String,
+    pub version: Option<String>,
+    pub old: T,
+    pub new: T,
+}
+
+/// The differences between two SBOMs.
+#[derive(Debug, Default, Serialize)]
+pub struct SbomDiff {
+    pub added: Vec<Component>,
+    pub removed: Vec<Component>,
+    pub upgraded: Vec<VersionChange>,
+    pub downgraded: Vec<VersionChange>,
+    pub license_changes: Vec<Change<Option<String>>>,
+    pub checksum_changes: Vec<Change<Vec<String>>>,
+}
+
+fn compare_optional_versions(a: &Option<String>, b: &Option<String>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => compare_versions(a, b),
+        _ => a.cmp(b),
+    }
+}
+
+impl SbomDiff {
+    /// Pairs packages by name and version, first those with the same checksums, so that e.g. the
+    /// unversioned sources and patches of a package aren't paired arbitrarily. Packages left
+    /// with a name and version that only one package on each side has are then paired too, and
+    /// their checksums changed. Of the packages left over after that, the ones sharing a name
+    /// are paired in version order when both sides have as many, which makes them version
+    /// changes. Anything else was added or removed.
+    pub fn new(old: &[Component], new: &[Component]) -> SbomDiff {
+        let mut by_name: BTreeMap<&str, (Vec<&Component>, Vec<&Component>)> = BTreeMap::new();
+        for component in old {
+            by_name
+                .entry(&component.name)
+                .or_default()
+                .0
+                .push(component);
+        }
+        for component in new {
+            by_name
+                .entry(&component.name)
+                .or_default()
+                .1
+                .push(component);
+        }
+
+        let mut diff = SbomDiff::default();
+        for (_, (mut old, mut new)) in by_name {
+            let mut unmatched_old = Vec::new();
+            for component in old.drain(..) {
+                match new.iter().position(|v| {
+                    v.version == component.version && v.checksums == component.checksums
+                }) {
+                    Some(index) => diff.compare(component, new.remove(index)),
+                    None => unmatched_old.push(component),
+                }
+            }
+
+            // Several packages left on either side with the same version can't be told apart.
+            let mut rest = Vec::new();
+            for &component in &unmatched_old {
+                let same_version = |v: &&&Component| v.version == component.version;
+                let old_count = unmatched_old.iter().filter(same_version).count();
+                let new_count = new.iter().filter(same_version).count();
+                if new_count == 0 {
+                    rest.push(component);
+                } else if old_count == 1 && new_count == 1 {
+                    let index = new
+                        .iter()
+                        .position(|v| same_version(&v))
+                        .unwrap_or_default();
+                    diff.compare(component, new.remove(index));
+                } else {
+                    diff.removed.push(component.clone());
+                }
+            }
+            let (ambiguous, mut new): (Vec<&Component>, Vec<&Component>) = new
+                .into_iter()
+                .partition(|v| unmatched_old.iter().any(|old| old.version == v.version));
+            diff.added.extend(ambiguous.into_iter().cloned());
+            let mut unmatched_old = rest;
+
+            if unmatched_old.len() == new.len() {
+                unmatched_old.sort_by(|a, b| compare_optional_versions(&a.version, &b.version));
+                new.sort_by(|a, b| compare_optional_versions(&a.version, &b.version));
+
+                for (old, new) in unmatched_old.into_iter().zip(new) {
+                    let change = VersionChange {
+                        name: old.name.clone(),
+                        old_version: old.version.clone(),
+                        new_version: new.version.clone(),
+                    };
+                    match compare_optional_versions(&old.version, &new.version) {
+                        Ordering::Greater => diff.downgraded.push(change),
+                        _ => diff.upgraded.push(change),
+                    }
+                    diff.compare_license(old, new);
+                }
+            } else {
+                diff.removed.extend(unmatched_old.into_iter().cloned());
+                diff.added.extend(new.into_iter().cloned());
+            }
+        }
+
+        diff
+    }
+
+    fn compare_license(&mut self, old: &Component, new: &Component) {
+        if old.license != new.license {
+            self.license_changes.push(Change {
+                name: new.name.clone(),
+                version: new.version.clone(),
+                old: old.license.clone(),
+                new: new.license.clone(),
+            });
+        }
+    }
+
+    /// Compares a package found in both SBOMs with the same version.
+    fn compare(&mut self, old: &Component, new: &Component) {
+        self.compare_license(old, new);
+        if old.checksums != new.checksums {
+            self.checksum_changes.push(Change {
+                name: new.name.clone(),
+                version: new.version.clone(),
+                old: old.checksums.clone(),
+                new: new.checksums.clone(),
+            });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.upgraded.is_empty()
+            && self.downgraded.is_empty()
+            && self.license_changes.is_empty()
+            && self.checksum_changes.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::json("the diff", e))
+    }
+
+    /// Renders the differences as text, one section per kind of change.
+    pub fn to_text(&self) -> String {
+        if self.is_empty() {
+            return "No differences".to_string();
+        }
+
+        let none = || "none".to_string();
+        let version = |v: &Option<String>| v.clone().unwrap_or_else(none);
+        let mut out = String::new();
+        let mut section = |title: &str, lines: Vec<String>| {
+            if !lines.is_empty() {
+                let _ = writeln!(out, "{}:", title);
+                for line in lines {
+                    let _ = writeln!(out, "  {}", line);
+                }
+            }
+        };
+
+        section(
+            "Added",
+            self.added
+                .iter()
+                .map(|v| format!("+ {}", v.label()))
+                .collect(),
+        );
+        section(
+            "Removed",
+            self.removed
+                .iter()
+                .map(|v| format!("- {}", v.label()))
+                .collect(),
+        );
+        for (title, changes) in [
+            ("Upgraded", &self.upgraded),
+            ("Downgraded", &self.downgraded),
+        ] {
+            section(
+                title,
+                changes
+                    .iter()
+                    .map(|v| {
+                        format!(
+                            "{} {} -> {}",
+                            v.name,
+                            version(&v.old_version),
+                            version(&v.new_version)
+                        )
+                    })
+                    .collect(),
+            );
+        }
+        section(
+            "License changes",
+            self.license_changes
+                .iter()
+                .map(|v| {
+                    format!(
+                        "{} {}: {} -> {}",
+                        v.name,
+                        version(&v.version),
+                        v.old.clone().unwrap_or_else(none),
+                        v.new.clone().unwrap_or_else(none)
+                    )
+                })
+                .collect(),
+        );
+        section(
+            "Checksum changes",
+            self.checksum_changes
+                .iter()
+                .map(|v| {
+                    let join = |v: &[String]| {
+                        Some(v.join(", "))
+                            .filter(|v| !v.is_empty())
+                            .unwrap_or_else(none)
+                    };
+                    format!(
+                        "{} {}: {} -> {}",
+                        v.name,
+                        version(&v.version),
+                        join(&v.old),
+                        join(&v.new)
+                    )
+                })
+                .collect(),
+        );
+
+        out.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(name: &str, version: Option<&str>, checksum: &str) -> Component {
+        Component {
+            name: name.to_string(),
+            version: version.map(str::to_string),
+            license: None,
+            checksums: vec![format!("SHA256:{}", checksum)],
+        }
+    }
+
+    #[test]
+    fn pairs_same_named_packages_on_checksums() {
+        let old = [
+            component("source", None, "aa"),
+            component("source", None, "bb"),
+        ];
+        let new = [
+            component("source", None, "bb"),
+            component("source", None, "aa"),
+        ];
+        assert!(SbomDiff::new(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn reports_ambiguous_packages_as_added_and_removed() {
+        let old = [
+            component("source", None, "aa"),
+            component("source", None, "bb"),
+        ];
+        let new = [
+            component("source", None, "aa"),
+            component("source", None, "cc"),
+            component("source", None, "dd"),
+        ];
+        let diff = SbomDiff::new(&old, &new);
+        assert_eq!(diff.removed, vec![old[1].clone()]);
+        assert_eq!(diff.added, vec![new[1].clone(), new[2].clone()]);
+        assert!(diff.checksum_changes.is_empty());
+    }
+
+    #[test]
+    fn reports_checksum_changes_of_unambiguous_packages() {
+        let old = [
+            component("hello", Some("2.10"), "aa"),
+            component("source", None, "bb"),
+        ];
+        let new = [
+            component("hello", Some("2.10"), "cc"),
+            component("source", None, "bb"),
+        ];
+        let diff = SbomDiff::new(&old, &new);
+        assert_eq!(diff.checksum_changes.len(), 1);
+        assert_eq!(diff.checksum_changes[0].old, vec!["SHA256:aa".to_string()]);
+        assert_eq!(diff.checksum_changes[0].new, vec!["SHA256:cc".to_string()]);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+    }
+
+    #[test]
+    fn pairs_leftover_versions_as_upgrades() {
+        let old = [
+            component("curl", Some("7.76.1"), "aa"),
+            component("source", None, "bb"),
+        ];
+        let new = [
+            component("curl", Some("7.79.0"), "cc"),
+            component("source", None, "bb"),
+        ];
+        let diff = SbomDiff::new(&old, &new);
+        assert_eq!(
+            diff.upgraded,
+            vec![VersionChange {
+                name: "curl".to_string(),
+                old_version: Some("7.76.1".to_string()),
+                new_version: Some("7.79.0".to_string()),
+            }]
+        );
+        assert!(diff.checksum_changes.is_empty() && diff.added.is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::process::Command;
+
+/// Everything that can go wrong while generating an SBOM.
+#[derive(Debug)]
+pub enum Error {
+    /// A nix command couldn't be run or exited unsuccessfully.
+    NixCommand {
+        command: String,
+        /// Exit code, `None` if the command was killed by a signal.
+        status: Option<i32>,
+        stderr: String,
+    },
+    /// Output of a nix command or a cached file isn't the JSON we expected.
+    Json {
+        /// What was being parsed, e.g. the command or file it came from.
+        context: String,
+        source: serde_json::Error,
+    },
+    /// A derivation lacks an environment attribute every derivation has.
+    MissingEnvAttribute { drv_path: String, attribute: String },
+    /// A `.drv` file isn't valid ATerm.
+    InvalidDerivation {
+        drv_path: String,
+        /// Byte offset the parser stopped at.
+        position: usize,
+        message: String,
+    },
+    /// Reading or writing a file failed.
+    Io {
+        /// What was being done, usually including the path.
+        context: String,
+        source: std::io::Error,
+    },
+    /// The caller asked for something that can't be done, e.g. parsing a Nix expression natively.
+    InvalidInput(String),
+}
+
+impl Error {
+    pub(crate) fn io(context: impl Into<String>, source: std::io::Error) -> Error {
+        Error::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn json(context: impl Into<String>, source: serde_json::Error) -> Error {
+        Error::Json {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Process exit code the command line reports this error with.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::InvalidInput(_) => 2,
+            Error::NixCommand { .. } => 3,
+            Error::Json { .. } => 4,
+            Error::MissingEnvAttribute { .. } => 5,
+            Error::Io { .. } => 6,
+            Error::InvalidDerivation { .. } => 7,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NixCommand {
+                command,
+                status,
+                stderr,
+            } => {
+                match status {
+                    Some(code) => write!(f, "`{}` failed with exit code {}", command, code)?,
+                    None => write!(f, "`{}` failed", command)?,
+                }
+                if !stderr.trim().is_empty() {
+                    write!(f, ":\n{}", stderr.trim_end())?;
+                }
+                Ok(())
+            }
+            Error::Json { context, source } => {
+                write!(f, "unexpected JSON from {}: {}", context, source)
+            }
+            Error::MissingEnvAttribute {
+                drv_path,
+                attribute,
+            } => write!(
+                f,
+                "derivation {} has no `{}` attribute, is it a valid .drv file?",
+                drv_path, attribute
+            ),
+            Error::InvalidDerivation {
+                drv_path,
+                position,
+                message,
+            } => write!(
+                f,
+                "invalid derivation {} at byte {}: {}",
+                drv_path, position, message
+            ),
+            Error::Io { context, source } => write!(f, "{}: {}", context, source),
+            Error::InvalidInput(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Json { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Arguments longer than this are left out of error messages, like a Nix expression passed with
+/// `--expr` or a JSON list of attributes.
+const MAX_SHOWN_ARGUMENT: usize = 200;
+
+/// Shows a command the way it was run, except for arguments too long or spanning lines, which
+/// would bury the error. Those are replaced by their size.
+fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|v| {
+            let v = v.to_string_lossy();
+            if v.contains('\n') || v.len() > MAX_SHOWN_ARGUMENT {
+                format!("<{} bytes>", v.len())
+            } else {
+                v.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Runs a command and returns its stdout, turning a missing binary or an unsuccessful exit into
+/// an `Error::NixCommand` that carries stderr.
+pub(crate) fn command_output(command: &mut Command) -> Result<Vec<u8>, Error> {
+    let output = command.output().map_err(|e| Error::NixCommand {
+        command: command_line(command),
+        status: None,
+        stderr: e.to_string(),
+    })?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(Error::NixCommand {
+            command: command_line(command),
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_lines_leave_out_long_arguments() {
+        let mut command = Command::new("nix-instantiate");
+        command
+            .arg("--eval")
+            .arg("--expr")
+            .arg("{ nixpkgs ? <nixpkgs> }:\nlet\n  pkgs = import nixpkgs { };\nin pkgs")
+            .arg("--argstr")
+            .arg("attrs")
+            .arg(format!("[{}]", vec!["[\"hello\"]"; 100].join(",")))
+            .arg("--argstr")
+            .arg("nixpkgs")
+            .arg("/nix/store/m0d6pgh3z5d1zyxymxqbsqwzhsf9jkgs-source");
+
+        assert_eq!(
+            command_line(&command),
+            "nix-instantiate --eval --expr <65 bytes> --argstr attrs <1001 bytes> \
+             --argstr nixpkgs /nix/store/m0d6pgh3z5d1zyxymxqbsqwzhsf9jkgs-source"
+        );
+    }
+}
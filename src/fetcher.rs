@@ -0,0 +1,91 @@
+// Recognises the nixpkgs fetchers (fetchurl, fetchzip, fetchgit, fetchFromGitHub, fetchsvn,
+// fetchhg and friends) from the environment of their fixed-output derivations.
+
+use crate::derivation::Drv;
+
+/// Where the output of a fetcher derivation was downloaded from.
+pub struct DownloadLocation {
+    /// A plain URL, or an SPDX VCS locator such as `git+https://host/repo@rev`.
+    pub locator: String,
+    /// Whether `locator` points into a version control system rather than at an archive.
+    pub is_vcs: bool,
+}
+
+/// The most common `mirror://` schemes from `pkgs/build-support/fetchurl/mirrors.nix`, resolved
+/// to one well known mirror each.
+const MIRRORS: &[(&str, &str)] = &[
+    ("apache", "https://www-eu.apache.org/dist/"),
+    ("cpan", "https://cpan.metacpan.org/"),
+    ("debian", "https://httpredir.debian.org/debian/"),
+    ("gnome", "https://download.gnome.org/"),
+    ("gnu", "https://ftpmirror.gnu.org/"),
+    ("hackage", "https://hackage.haskell.org/package/"),
+    ("mozilla", "https://download.cdn.mozilla.net/pub/"),
+    ("pypi", "https://files.pythonhosted.org/packages/source/"),
+    ("savannah", "https://download.savannah.gnu.org/releases/"),
+    ("sourceforge", "https://downloads.sourceforge.net/"),
+    ("xorg", "https://xorg.freedesktop.org/releases/"),
+];
+
+fn resolve_mirror(url: &str) -> String {
+    url.strip_prefix("mirror://")
+        .and_then(|rest| {
+            let (mirror, path) = rest.split_once('/')?;
+            MIRRORS
+                .iter()
+                .find(|(name, _)| *name == mirror)
+                .map(|(_, base)| format!("{}{}", base, path))
+        })
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Returns the version control system a `url` and `rev` pair was fetched with, going by the
+/// attributes only the respective fetcher passes to its builder.
+fn vcs_tool(drv: &Drv, url: &str) -> &'static str {
+    let has = |attribute: &str| drv.env.contains_key(attribute);
+
+    if has("ignoreExternals") || url.starts_with("svn") {
+        "svn"
+    } else if has("fetchSubrepos") || url.starts_with("hg") {
+        "hg"
+    } else {
+        // fetchgit and everything built on it (fetchFromGitHub with submodules, fetchFromGitLab…)
+        "git"
+    }
+}
+
+pub fn is_fixed_output(drv: &Drv) -> bool {
+    drv.env.contains_key("outputHash") || drv.outputs.values().any(|v| v.hash.is_some())
+}
+
+/// Returns where a fetcher derivation downloads its output from, or `None` for anything that
+/// isn't a fixed-output derivation with a `url`/`urls` attribute.
+pub fn download_location(drv: &Drv) -> Option<DownloadLocation> {
+    if !is_fixed_output(drv) {
+        return None;
+    }
+
+    let url = drv
+        .env
+        .get("urls")
+        .and_then(|v| v.split_whitespace().next())
+        .or_else(|| drv.env.get("url").map(String::as_str))
+        .filter(|v| !v.is_empty())?;
+
+    match drv.env.get("rev").filter(|v| !v.is_empty()) {
+        Some(rev) => {
+            let tool = vcs_tool(drv, url);
+            let prefix = format!("{}+", tool);
+            let url = url.strip_prefix(&prefix).unwrap_or(url);
+
+            Some(DownloadLocation {
+                locator: format!("{}{}@{}", prefix, url, rev),
+                is_vcs: true,
+            })
+        }
+        None => Some(DownloadLocation {
+            locator: resolve_mirror(url),
+            is_vcs: false,
+        }),
+    }
+}
@@ -0,0 +1,213 @@
+// Reads the nixpkgs a flake is locked to from its flake.lock, so metadata can be evaluated from
+// the same revision the derivations were built from.
+
+use crate::error::{command_output, Error};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+/// A locked flake input.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LockedInput {
+    /// Flake reference pinned to the locked revision, e.g. `github:NixOS/nixpkgs/<rev>`.
+    pub reference: String,
+    /// `narHash` of the input's source tree, in SRI format.
+    pub nar_hash: Option<String>,
+}
+
+/// Resolves an input of a node, following `follows` paths like `["utils", "nixpkgs"]`.
+fn resolve_input<'a>(lock: &'a Value, node: &str, input: &str) -> Option<&'a str> {
+    match &lock["nodes"][node]["inputs"][input] {
+        Value::String(name) => Some(name),
+        Value::Array(path) => {
+            let root = lock["root"].as_str()?;
+            path.iter().try_fold(root, |node, input| {
+                resolve_input(lock, node, input.as_str()?)
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Turns the `locked` attributes of a flake.lock node back into a flake reference.
+pub(crate) fn locked_reference(locked: &Value) -> Option<String> {
+    let get = |attribute: &str| locked[attribute].as_str();
+
+    match get("type")? {
+        kind @ ("github" | "gitlab" | "sourcehut") => {
+            let mut reference = format!(
+                "{}:{}/{}/{}",
+                kind,
+                get("owner")?,
+                get("repo")?,
+                get("rev")?
+            );
+            if let Some(host) = get("host") {
+                reference.push_str(&format!("?host={}", host));
+            }
+            Some(reference)
+        }
+        "git" => Some(format!("git+{}?rev={}", get("url")?, get("rev")?)),
+        "path" => Some(format!("path:{}", get("path")?)),
+        "tarball" => Some(get("url")?.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the nixpkgs the flake in `dir` is locked to, or `None` if there is no flake.lock or
+/// it has no `nixpkgs` input.
+pub(crate) fn locked_nixpkgs(dir: &Path) -> Result<Option<LockedInput>, Error> {
+    let path = dir.join("flake.lock");
+    let contents = match std::fs::read(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::io(format!("reading {}", path.display()), e)),
+    };
+    let lock: Value = serde_json::from_slice(&contents)
+        .map_err(|e| Error::json(path.display().to_string(), e))?;
+
+    lock_nixpkgs(&lock, &path.display().to_string())
+}
+
+/// Returns the nixpkgs a remote flake such as `github:org/repo` is locked to, reading its lock
+/// with `nix flake metadata`, which fetches the flake.
+pub(crate) fn remote_locked_nixpkgs(reference: &str) -> Result<Option<LockedInput>, Error> {
+    let output = command_output(
+        Command::new("nix")
+            .arg("--extra-experimental-features")
+            .arg("nix-command flakes")
+            .arg("flake")
+            .arg("metadata")
+            .arg("--json")
+            .arg(reference),
+    )?;
+    let metadata: Value = serde_json::from_slice(&output)
+        .map_err(|e| Error::json(format!("nix flake metadata {}", reference), e))?;
+
+    metadata_nixpkgs(&metadata, reference)
+}
+
+/// Picks the nixpkgs out of `nix flake metadata --json` output: the flake's `nixpkgs` input, or
+/// the flake itself if it is nixpkgs, as for `github:NixOS/nixpkgs#hello`.
+fn metadata_nixpkgs(metadata: &Value, reference: &str) -> Result<Option<LockedInput>, Error> {
+    if let Some(locked) = lock_nixpkgs(&metadata["locks"], &format!("the lock of {}", reference))? {
+        return Ok(Some(locked));
+    }
+
+    let locked = &metadata["locked"];
+    if !is_nixpkgs(locked) {
+        return Ok(None);
+    }
+
+    Ok(locked_reference(locked).map(|reference| LockedInput {
+        reference,
+        nar_hash: locked["narHash"].as_str().map(|v| v.to_string()),
+    }))
+}
+
+/// Whether the `locked` attributes of a flake point at a nixpkgs repository, a fork included.
+fn is_nixpkgs(locked: &Value) -> bool {
+    let get = |attribute: &str| locked[attribute].as_str().unwrap_or_default();
+
+    match get("type") {
+        "github" | "gitlab" | "sourcehut" => get("repo") == "nixpkgs",
+        "git" => {
+            let url = get("url").split(['?', '#']).next().unwrap_or_default();
+            let url = url.trim_end_matches('/');
+            let url = url.strip_suffix(".git").unwrap_or(url);
+            url.rsplit('/').next() == Some("nixpkgs")
+        }
+        _ => false,
+    }
+}
+
+/// Looks up the `nixpkgs` input of the root node of a parsed flake.lock, `source` naming where it
+/// came from for errors.
+fn lock_nixpkgs(lock: &Value, source: &str) -> Result<Option<LockedInput>, Error> {
+    let node = lock["root"]
+        .as_str()
+        .and_then(|root| resolve_input(lock, root, "nixpkgs"));
+    let locked = match node {
+        Some(node) => &lock["nodes"][node]["locked"],
+        None => return Ok(None),
+    };
+
+    match locked_reference(locked) {
+        Some(reference) => Ok(Some(LockedInput {
+            reference,
+            nar_hash: locked["narHash"].as_str().map(|v| v.to_string()),
+        })),
+        None => Err(Error::InvalidInput(format!(
+            "{} locks nixpkgs with an unsupported input type, pass --nixpkgs instead",
+            source
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const METADATA: &str = include_str!("../tests/fixtures/nixpkgs-flake-metadata.json");
+
+    #[test]
+    fn nixpkgs_itself_is_its_own_nixpkgs() {
+        let metadata = serde_json::from_str(METADATA).unwrap();
+
+        assert_eq!(
+            metadata_nixpkgs(&metadata, "github:NixOS/nixpkgs").unwrap(),
+            Some(LockedInput {
+                reference: "github:NixOS/nixpkgs/5e4c2ada4fcd54b99d56d7bd62f384511a7e2593"
+                    .to_string(),
+                nar_hash: Some("sha256-9NJcFF9CEYPvHJ5ckE8kvINvI84SZZ87PvqMbH6pro0=".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn nixpkgs_inputs_come_first() {
+        let lock: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/flake/flake.lock")).unwrap();
+        let mut metadata: Value = serde_json::from_str(METADATA).unwrap();
+        metadata["locks"] = lock;
+
+        assert_eq!(
+            metadata_nixpkgs(&metadata, "github:NixOS/nixpkgs")
+                .unwrap()
+                .map(|v| v.reference),
+            Some("github:NixOS/nixpkgs/c06613c25df3fe1dd26243847a3c105cf6770627".to_string())
+        );
+    }
+
+    #[test]
+    fn other_flakes_without_nixpkgs_have_none() {
+        let mut metadata: Value = serde_json::from_str(METADATA).unwrap();
+        metadata["locked"]["owner"] = json!("numtide");
+        metadata["locked"]["repo"] = json!("flake-utils");
+
+        assert_eq!(
+            metadata_nixpkgs(&metadata, "github:numtide/flake-utils").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn recognises_nixpkgs_repositories() {
+        for locked in [
+            json!({"type": "github", "owner": "NixOS", "repo": "nixpkgs"}),
+            json!({"type": "github", "owner": "someone", "repo": "nixpkgs"}),
+            json!({"type": "git", "url": "https://github.com/NixOS/nixpkgs.git"}),
+            json!({"type": "git", "url": "file:///home/jane/src/nixpkgs?ref=master"}),
+        ] {
+            assert!(is_nixpkgs(&locked), "{}", locked);
+        }
+        for locked in [
+            json!({"type": "github", "owner": "NixOS", "repo": "nix"}),
+            json!({"type": "git", "url": "https://github.com/NixOS/nixpkgs-channels-tools"}),
+            json!({"type": "path", "path": "/home/jane/src/nixpkgs"}),
+        ] {
+            assert!(!is_nixpkgs(&locked), "{}", locked);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+// Package URLs (purl) and CPE 2.3 names, which vulnerability databases use to correlate SBOM
+// entries with advisories.
+
+use crate::derivation::Drv;
+use crate::fetcher;
+use crate::nixpkgs::Package;
+
+/// The identifiers of a single package.
+pub struct Identifiers {
+    /// `pkg:nix` purl of the nixpkgs package.
+    pub purl: String,
+    /// purl of the package in its upstream ecosystem, when the builder gives it away.
+    pub upstream_purl: Option<String>,
+    /// CPE 2.3 name, for matching against the NVD. CPE names are limited to ASCII, so packages
+    /// with other characters in their name or version have none.
+    pub cpe: Option<String>,
+}
+
+/// nixpkgs `pname`s whose CPE vendor or product differs from the `pname` itself, as
+/// `(pname, vendor, product)`.
+const CPE_OVERRIDES: &[(&str, &str, &str)] = &[
+    ("bash", "gnu", "bash"),
+    ("binutils", "gnu", "binutils"),
+    ("bzip2", "bzip", "bzip2"),
+    ("coreutils", "gnu", "coreutils"),
+    ("curl", "haxx", "curl"),
+    ("emacs", "gnu", "emacs"),
+    ("expat", "libexpat_project", "libexpat"),
+    ("gcc", "gnu", "gcc"),
+    ("git", "git-scm", "git"),
+    ("glibc", "gnu", "glibc"),
+    ("gnupg", "gnupg", "gnupg"),
+    ("gnutls", "gnu", "gnutls"),
+    ("go", "golang", "go"),
+    ("krb5", "mit", "kerberos_5"),
+    ("libgcrypt", "gnupg", "libgcrypt"),
+    ("libxml2", "xmlsoft", "libxml2"),
+    ("libxslt", "xmlsoft", "libxslt"),
+    ("ncurses", "gnu", "ncurses"),
+    ("nodejs", "nodejs", "node.js"),
+    ("openssh", "openbsd", "openssh"),
+    ("perl", "perl", "perl"),
+    ("python", "python", "python"),
+    ("python3", "python", "python"),
+    ("readline", "gnu", "readline"),
+    ("ruby", "ruby-lang", "ruby"),
+    ("rustc", "rust-lang", "rust"),
+    ("sudo", "sudo_project", "sudo"),
+    ("systemd", "systemd_project", "systemd"),
+    ("tar", "gnu", "tar"),
+    ("wget", "gnu", "wget"),
+    ("xz", "tukaani", "xz"),
+];
+
+/// Percent-encodes the UTF-8 bytes of everything but the characters purl allows unencoded in
+/// names and versions.
+fn purl_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~+".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+/// Builds a Package URL for a nixpkgs package. `nix` isn't a registered purl type yet, but it is
+/// what other Nix SBOM tooling settled on.
+fn nix_purl(name: &str, version: &str) -> String {
+    format!("pkg:nix/{}@{}", purl_encode(name), purl_encode(version))
+}
+
+/// Returns the purl of the upstream package a derivation was built from, for the builders that
+/// make this detectable: `buildPythonPackage` prefixes the name with the interpreter, and
+/// `buildRustPackage`/`fetchCrate` carry cargo specific attributes.
+fn upstream_purl(drv: &Drv, name: &str, version: &str) -> Option<String> {
+    let drv_name = drv.env.get("name").map(String::as_str).unwrap_or_default();
+    let interpreter = drv_name.split('-').next().filter(|v| {
+        v.strip_prefix("python")
+            .is_some_and(|v| v.starts_with(|c: char| c.is_ascii_digit()))
+    });
+    let is_cargo = drv.env.contains_key("cargoDeps")
+        || drv.env.contains_key("cargoVendorDir")
+        || drv
+            .env
+            .get("urls")
+            .is_some_and(|v| v.contains("crates.io/api/v1/crates/"));
+
+    // The interpreter itself is named like python3-3.9.6, its packages like python3.9-requests-2.25.1
+    let is_interpreter = |interpreter: &str| {
+        drv_name[interpreter.len()..]
+            .trim_start_matches('-')
+            .starts_with(|c: char| c.is_ascii_digit())
+    };
+
+    match interpreter {
+        Some(interpreter) if !is_interpreter(interpreter) => {
+            let name = name
+                .strip_prefix(&format!("{}-", interpreter))
+                .unwrap_or(name);
+            Some(format!(
+                "pkg:pypi/{}@{}",
+                purl_encode(&name.to_lowercase().replace('_', "-")),
+                purl_encode(version)
+            ))
+        }
+        _ if is_cargo => Some(format!(
+            "pkg:cargo/{}@{}",
+            purl_encode(name),
+            purl_encode(version)
+        )),
+        _ => None,
+    }
+}
+
+/// Quotes the ASCII punctuation CPE 2.3 formatted strings don't allow unescaped. Anything
+/// outside of printable ASCII can't be expressed at all.
+fn cpe_escape(value: &str) -> Option<String> {
+    value
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || "-._".contains(c) => Some(c.to_string()),
+            c if c.is_ascii_punctuation() => Some(format!("\\{}", c)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Builds a CPE 2.3 application name for a package, guessing `vendor:product` as
+/// `pname:pname` unless `CPE_OVERRIDES` knows better.
+fn cpe(name: &str, version: &str) -> Option<String> {
+    let (vendor, product) = CPE_OVERRIDES
+        .iter()
+        .find(|(pname, _, _)| *pname == name)
+        .map(|(_, vendor, product)| (*vendor, *product))
+        .unwrap_or((name, name));
+
+    Some(format!(
+        "cpe:2.3:a:{}:{}:{}:*:*:*:*:*:*:*",
+        cpe_escape(vendor)?,
+        cpe_escape(product)?,
+        cpe_escape(version)?
+    ))
+}
+
+impl Identifiers {
+    pub fn new(drv: &Drv, name: &str, version: &str) -> Identifiers {
+        Identifiers {
+            purl: nix_purl(name, version),
+            upstream_purl: upstream_purl(drv, name, version),
+            cpe: cpe(name, version),
+        }
+    }
+}
+
+/// Returns the purls and CPE name of a derivation, preferring the nixpkgs pname and version over
+/// the ones parsed from the derivation name. Sources get none, they would only duplicate the
+/// identifiers of the package they're fetched for.
+pub(crate) fn get_identifiers(drv: &Drv, package: Option<&Package>) -> Option<Identifiers> {
+    if fetcher::is_fixed_output(drv) {
+        return None;
+    }
+
+    match package {
+        Some(package) => Some(Identifiers::new(drv, &package.pname, &package.version)),
+        None => {
+            let (name, version) = drv.name_and_version();
+            Some(Identifiers::new(drv, &name, &version?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purl_encodes_utf8_bytes() {
+        assert_eq!(nix_purl("café", "1.0"), "pkg:nix/caf%C3%A9@1.0");
+        assert_eq!(nix_purl("gtk+3", "3.24.1"), "pkg:nix/gtk+3@3.24.1");
+        assert_eq!(nix_purl("a b", "1/2"), "pkg:nix/a%20b@1%2F2");
+    }
+
+    #[test]
+    fn cpe_escapes_ascii_punctuation() {
+        assert_eq!(
+            cpe("curl", "7.76.1").as_deref(),
+            Some("cpe:2.3:a:haxx:curl:7.76.1:*:*:*:*:*:*:*")
+        );
+        assert_eq!(
+            cpe("gtk+", "3.24:1").as_deref(),
+            Some("cpe:2.3:a:gtk\\+:gtk\\+:3.24\\:1:*:*:*:*:*:*:*")
+        );
+        assert_eq!(cpe("café", "1.0"), None);
+    }
+}
@@ -0,0 +1,134 @@
+// The kinds of things an SBOM can be generated for, and telling them apart from the command line
+// argument alone.
+
+use std::path::Path;
+
+/// URL-like flake reference schemes understood by `nix`.
+pub(crate) const FLAKE_SCHEMES: &[&str] = &[
+    "flake:",
+    "path:",
+    "git+",
+    "hg+",
+    "tarball+",
+    "file+",
+    "github:",
+    "gitlab:",
+    "sourcehut:",
+    "http://",
+    "https://",
+];
+
+/// What to generate an SBOM for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// A Nix expression file evaluating to a single derivation, e.g. `default.nix`.
+    Expression(String),
+    /// A flake reference or installable, e.g. `.#packages.x86_64-linux.default` or
+    /// `github:NixOS/nixpkgs#hello`.
+    Flake(String),
+    /// A `.drv` store path.
+    DrvPath(String),
+    /// The store path of a realised output, resolved to the derivation that produced it.
+    OutputPath(String),
+}
+
+impl Input {
+    /// Guesses the kind of input from its syntax: store paths are recognised by the store
+    /// directory, flakes by a `#` fragment, a flake URL scheme or a directory holding a
+    /// `flake.nix`. Anything else is taken to be an expression file.
+    pub fn detect(input: &str) -> Input {
+        let store_dir = store_dir();
+        let is_store_path = input
+            .strip_prefix(&store_dir)
+            .is_some_and(|v| v.starts_with('/'));
+
+        if is_store_path && input.ends_with(".drv") {
+            Input::DrvPath(input.to_string())
+        } else if is_store_path {
+            Input::OutputPath(input.to_string())
+        } else if input.contains('#')
+            || FLAKE_SCHEMES.iter().any(|v| input.starts_with(v))
+            || Path::new(input).join("flake.nix").is_file()
+        {
+            Input::Flake(input.to_string())
+        } else {
+            Input::Expression(input.to_string())
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Input::Expression(v) | Input::Flake(v) | Input::DrvPath(v) | Input::OutputPath(v) => v,
+        }
+    }
+}
+
+/// The Nix store directory, `/nix/store` unless overridden like `nix` itself allows.
+pub(crate) fn store_dir() -> String {
+    std::env::var("NIX_STORE_DIR")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/nix/store".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAKE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/flake");
+
+    #[test]
+    fn detects_flake_references() {
+        assert_eq!(Input::detect(".#pkg"), Input::Flake(".#pkg".to_string()));
+        assert_eq!(
+            Input::detect("github:o/r#x"),
+            Input::Flake("github:o/r#x".to_string())
+        );
+        assert_eq!(
+            Input::detect("github:o/r"),
+            Input::Flake("github:o/r".to_string())
+        );
+    }
+
+    #[test]
+    fn detects_flake_directories() {
+        assert_eq!(Input::detect(FLAKE), Input::Flake(FLAKE.to_string()));
+
+        // A directory without a flake.nix is left to nix-instantiate, which reads its default.nix
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/store");
+        assert_eq!(Input::detect(dir), Input::Expression(dir.to_string()));
+    }
+
+    #[test]
+    fn detects_store_paths() {
+        let drv = format!(
+            "{}/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10.drv",
+            store_dir()
+        );
+        assert_eq!(Input::detect(&drv), Input::DrvPath(drv.clone()));
+
+        let out = format!(
+            "{}/xbhlhfz3lzqcqzm8hf8n9ymzcv7c3g6v-hello-2.10",
+            store_dir()
+        );
+        assert_eq!(Input::detect(&out), Input::OutputPath(out.clone()));
+
+        // Only paths inside the store directory, not ones merely starting with its name
+        let sibling = format!("{}-old/hello.drv", store_dir());
+        assert_eq!(Input::detect(&sibling), Input::Expression(sibling.clone()));
+    }
+
+    #[test]
+    fn detects_expression_files() {
+        assert_eq!(
+            Input::detect("default.nix"),
+            Input::Expression("default.nix".to_string())
+        );
+        assert_eq!(
+            Input::detect("./pkgs/hello.nix"),
+            Input::Expression("./pkgs/hello.nix".to_string())
+        );
+    }
+}
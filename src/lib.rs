@@ -0,0 +1,315 @@
+//! Generates SPDX and CycloneDX Software Bills of Materials for Nix derivations.
+//!
+//! Generation happens in three stages: an [`SbomBuilder`] loads the closure of a derivation into
+//! a [`DerivationGraph`], the graph is enriched with nixpkgs metadata from a [`PackageIndex`],
+//! and the resulting [`Sbom`] is rendered in one of the [`OutputFormat`]s.
+//!
+//! ```no_run
+//! use nixbom::{Cache, DocumentInfo, Input, Nixpkgs, OutputFormat, PackageIndex, SbomBuilder};
+//!
+//! # fn main() -> Result<(), nixbom::Error> {
+//! let input = Input::detect(".#packages.x86_64-linux.default");
+//! let nixpkgs = Nixpkgs::detect(&input)?;
+//! let sbom = SbomBuilder::new(input.as_str())
+//!     .depth(Some(1))
+//!     .load()?
+//!     .enrich(PackageIndex::load(&nixpkgs, Some(&Cache::new(None)?))?);
+//! let info = DocumentInfo::new("my-project", vec!["Jane Doe".to_string()]);
+//! println!("{}", sbom.render(&info, OutputFormat::SpdxJson)?);
+//! # Ok(())
+//! # }
+//! ```
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+mod advisory;
+mod aterm;
+mod cache;
+mod cyclonedx;
+pub mod cyclonedx_spec;
+mod cyclonedx_xml;
+mod derivation;
+mod diff;
+mod error;
+mod fetcher;
+mod flake_lock;
+mod identifiers;
+mod input;
+mod license;
+mod nix_hash;
+mod nixpkgs;
+mod scan;
+mod spdx;
+mod spdx_licenses;
+pub mod spdx_spec;
+mod spdx_tag_value;
+mod version;
+
+use chrono::prelude::*;
+use cyclonedx_spec::Bom;
+use derivation::Derivation;
+use spdx_spec::SpdxSchema;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+pub use advisory::{Advisory, AdvisoryDb};
+pub use cache::{Cache, NixpkgsId};
+pub use derivation::{Closure, DerivationGraph, DerivationSource, Drv, InputKind, Out};
+pub use diff::{read_components, Change, Component, SbomDiff, VersionChange};
+pub use error::Error;
+pub use input::Input;
+pub use nixpkgs::{Nixpkgs, Package, PackageIndex};
+pub use scan::{findings_json, findings_table, Finding, FindingStatus};
+pub use spdx::SpdxVersion;
+
+/// Output formats the SBOM can be rendered in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    SpdxJson,
+    SpdxTagValue,
+    CycloneDxJson,
+    CycloneDxXml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spdx-json" => Ok(OutputFormat::SpdxJson),
+            "spdx-tag-value" => Ok(OutputFormat::SpdxTagValue),
+            "cyclonedx-json" => Ok(OutputFormat::CycloneDxJson),
+            "cyclonedx-xml" => Ok(OutputFormat::CycloneDxXml),
+            _ => Err(format!("unsupported output format: {}", s)),
+        }
+    }
+}
+
+/// Document level information that doesn't come from the derivations.
+#[derive(Debug, Clone)]
+pub struct DocumentInfo {
+    /// Name of the project the SBOM is generated for.
+    pub name: String,
+    /// Authors of the SBOM. SPDX creators get a `Person: ` prefix unless they already have one.
+    pub authors: Vec<String>,
+    /// Creation timestamp, as `%Y-%m-%dT%H:%M:%SZ`.
+    pub created: String,
+    /// License of the SPDX document itself.
+    pub data_license: String,
+    /// Base URI of the SPDX document namespace.
+    pub namespace: String,
+    pub spdx_version: SpdxVersion,
+    pub cyclonedx_version: String,
+}
+
+impl DocumentInfo {
+    /// Creates the document information with the same defaults as the command line, created now.
+    pub fn new(name: &str, authors: Vec<String>) -> DocumentInfo {
+        DocumentInfo {
+            name: name.to_string(),
+            authors,
+            created: Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            data_license: "CC0-1.0".to_string(),
+            namespace: "https://spdx.org/spdxdocs".to_string(),
+            spdx_version: SpdxVersion::V2_3,
+            cyclonedx_version: "1.5".to_string(),
+        }
+    }
+}
+
+/// Loads the derivation graph an SBOM is generated for.
+pub struct SbomBuilder {
+    input: Input,
+    source: DerivationSource,
+    depth: Option<usize>,
+    nar_hashes: bool,
+    runtime: bool,
+}
+
+impl SbomBuilder {
+    /// Starts from a Nix expression file, a flake reference, a `.drv` path or an output store
+    /// path, telling them apart with `Input::detect`.
+    pub fn new(derivation: &str) -> SbomBuilder {
+        SbomBuilder {
+            input: Input::detect(derivation),
+            source: DerivationSource::Nix,
+            depth: None,
+            nar_hashes: false,
+            runtime: false,
+        }
+    }
+
+    /// Overrides the detected kind of input.
+    pub fn input(mut self, input: Input) -> SbomBuilder {
+        self.input = input;
+        self
+    }
+
+    pub fn source(mut self, source: DerivationSource) -> SbomBuilder {
+        self.source = source;
+        self
+    }
+
+    /// How many levels of input derivations to include, `None` for the whole closure.
+    pub fn depth(mut self, depth: Option<usize>) -> SbomBuilder {
+        self.depth = depth;
+        self
+    }
+
+    /// Whether to look up the NAR hash of realised outputs that aren't fixed-output derivations.
+    pub fn nar_hashes(mut self, nar_hashes: bool) -> SbomBuilder {
+        self.nar_hashes = nar_hashes;
+        self
+    }
+
+    /// Only includes what the realised outputs reference at runtime, instead of everything
+    /// needed to build them. Needs the Nix database even when reading derivations natively.
+    pub fn runtime(mut self, runtime: bool) -> SbomBuilder {
+        self.runtime = runtime;
+        self
+    }
+
+    pub fn load(self) -> Result<DerivationGraph, Error> {
+        if self.runtime {
+            return self.load_runtime();
+        }
+
+        let root_derivations = self.load_root()?;
+        let root = root_derivations
+            .keys()
+            .next()
+            .cloned()
+            .ok_or_else(|| Error::InvalidInput("no derivation found".to_string()))?;
+
+        let mut derivations = root_derivations.get_input_derivations(self.depth, &self.source)?;
+        derivations.extend(root_derivations);
+        let mut graph = DerivationGraph::new(root, derivations);
+        if self.nar_hashes {
+            graph.add_nar_hashes()?;
+        }
+
+        Ok(graph)
+    }
+
+    /// Starts from the given output path, or from the realised outputs of the root derivation.
+    fn load_runtime(self) -> Result<DerivationGraph, Error> {
+        let mut out_paths = match &self.input {
+            Input::OutputPath(path) => vec![path.clone()],
+            _ => self
+                .load_root()?
+                .values()
+                .flat_map(|drv| drv.outputs.values())
+                .map(|v| v.path.clone())
+                .filter(|v| Path::new(v).exists())
+                .collect(),
+        };
+        out_paths.sort();
+        if out_paths.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "{} isn't realised, build it before generating a runtime SBOM",
+                self.input.as_str()
+            )));
+        }
+
+        let mut graph = derivation::get_runtime_closure(&out_paths, self.depth, &self.source)?;
+        if self.nar_hashes {
+            graph.add_nar_hashes()?;
+        }
+
+        Ok(graph)
+    }
+
+    fn load_root(&self) -> Result<HashMap<String, Drv>, Error> {
+        let input = match &self.input {
+            Input::OutputPath(path) => Input::DrvPath(derivation::get_deriver(path)?),
+            input => input.clone(),
+        };
+        let root_derivations: HashMap<String, Drv> = match (&self.source, &input) {
+            (_, Input::DrvPath(path)) => self.source.get_derivation(path)?,
+            (DerivationSource::Nix, input) => derivation::show_derivation(input)?,
+            (DerivationSource::Store(_), input) => {
+                return Err(Error::InvalidInput(format!(
+                    "{} has to be evaluated by nix, reading derivations natively needs a .drv or output store path",
+                    input.as_str()
+                )))
+            }
+        };
+
+        Ok(root_derivations)
+    }
+}
+
+/// A derivation graph along with the nixpkgs metadata to describe it with.
+pub struct Sbom {
+    pub graph: DerivationGraph,
+    pub package_index: PackageIndex,
+}
+
+impl DerivationGraph {
+    pub fn enrich(self, package_index: PackageIndex) -> Sbom {
+        Sbom {
+            graph: self,
+            package_index,
+        }
+    }
+}
+
+impl Sbom {
+    /// Drops every derivation that couldn't be matched with nixpkgs metadata.
+    pub fn retain_nixpkgs(&mut self) {
+        let package_index = &self.package_index;
+        self.graph
+            .derivations
+            .retain(|path, drv| package_index.find(path, drv).is_some());
+    }
+
+    /// Returns the sorted store paths of derivations without nixpkgs metadata.
+    pub fn unmatched(&self) -> Vec<&String> {
+        let mut unmatched = self
+            .graph
+            .derivations
+            .iter()
+            .filter(|(path, drv)| self.package_index.find(path, drv).is_none())
+            .map(|(path, _)| path)
+            .collect::<Vec<&String>>();
+        unmatched.sort();
+
+        unmatched
+    }
+
+    /// Matches the packages against the advisories in `db` and the `knownVulnerabilities` of
+    /// their nixpkgs metadata.
+    pub fn scan(&self, db: &AdvisoryDb) -> Vec<Finding> {
+        scan::scan(&self.graph, &self.package_index, db)
+    }
+
+    /// Returns the packages as they appear in the SPDX document, to compare with another SBOM.
+    pub fn components(&self) -> Vec<Component> {
+        diff::spdx_components(&self.to_spdx(&DocumentInfo::new("diff", Vec::new())))
+    }
+
+    pub fn to_spdx(&self, info: &DocumentInfo) -> SpdxSchema {
+        SpdxSchema::new(info, &self.graph, &self.package_index)
+    }
+
+    pub fn to_cyclonedx(&self, info: &DocumentInfo) -> Bom {
+        Bom::new(info, &self.graph, &self.package_index)
+    }
+
+    pub fn render(&self, info: &DocumentInfo, format: OutputFormat) -> Result<String, Error> {
+        let output = match format {
+            OutputFormat::SpdxJson => serde_json::to_string_pretty(&self.to_spdx(info))
+                .map_err(|e| Error::json("the SPDX document", e))?,
+            OutputFormat::SpdxTagValue => self.to_spdx(info).to_tag_value(),
+            OutputFormat::CycloneDxJson => serde_json::to_string_pretty(&self.to_cyclonedx(info))
+                .map_err(|e| Error::json("the CycloneDX BOM", e))?,
+            OutputFormat::CycloneDxXml => self.to_cyclonedx(info).to_xml(),
+        };
+
+        Ok(output)
+    }
+}
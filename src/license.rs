@@ -0,0 +1,276 @@
+// Turns nixpkgs `meta.license` values (attrsets from lib.licenses, bare names and arbitrarily
+// nested lists of both) into SPDX license expressions.
+
+use crate::nixpkgs::{License, Licenses};
+use crate::spdx_licenses::{DEPRECATED, EXCEPTIONS, LICENSES, LICENSE_LIST_VERSION};
+
+/// A license that isn't on the SPDX license list, referenced as `LicenseRef-…` from expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedLicense {
+    pub license_id: String,
+    pub name: String,
+    pub see_also: Option<String>,
+    /// Why the license couldn't be mapped to the SPDX License List.
+    pub comment: Option<String>,
+}
+
+/// An SPDX license expression along with the non-SPDX licenses it references.
+#[derive(Debug, Clone)]
+pub struct LicenseExpression {
+    pub expression: String,
+    pub extracted: Vec<ExtractedLicense>,
+}
+
+impl LicenseExpression {
+    /// Whether the expression is a single license from the SPDX license list.
+    pub fn is_spdx_id(&self) -> bool {
+        self.extracted.is_empty() && !self.expression.contains(' ')
+    }
+}
+
+/// Characters of a license id in an expression, including the `+` or-later operator.
+fn is_id_char(c: char) -> bool {
+    is_idstring_char(c) || c == '+'
+}
+
+/// Characters SPDX allows in `LicenseRef-` idstrings.
+fn is_idstring_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '-'
+}
+
+/// Builds a `LicenseRef-` id from a license name. A trailing `+` reads as or-later, like in
+/// `LGPLv2+`, and is spelled out so the id stays distinct from the one without it.
+fn license_ref(name: &str) -> String {
+    let (name, or_later) = match name.strip_suffix('+') {
+        Some(name) => (name, "-or-later"),
+        None => (name, ""),
+    };
+    let id = name
+        .chars()
+        .map(|c| if is_idstring_char(c) { c } else { '-' })
+        .collect::<String>();
+
+    format!("LicenseRef-{}{}", id.trim_matches('-'), or_later)
+}
+
+/// Canonicalises a license id against the SPDX License List, correcting its case and replacing
+/// deprecated ids such as `GPL-2.0+`.
+fn canonical_id(id: &str) -> Option<&'static str> {
+    LICENSES
+        .iter()
+        .map(|(id, _)| (*id, *id))
+        .chain(DEPRECATED.iter().copied())
+        .find(|(v, _)| v.eq_ignore_ascii_case(id))
+        .map(|(_, id)| id)
+}
+
+/// Canonicalises a license exception id such as `LLVM-exception`, correcting its case.
+fn canonical_exception(id: &str) -> Option<&'static str> {
+    EXCEPTIONS
+        .iter()
+        .copied()
+        .find(|v| v.eq_ignore_ascii_case(id))
+}
+
+/// Looks up the id of a license by its full name on the SPDX License List.
+fn id_for_name(name: &str) -> Option<&'static str> {
+    LICENSES
+        .iter()
+        .find(|(_, v)| v.eq_ignore_ascii_case(name))
+        .map(|(id, _)| *id)
+}
+
+fn extracted_term(
+    name: &str,
+    short_name: Option<&str>,
+    see_also: Option<&str>,
+    unknown_id: Option<&str>,
+) -> (String, Option<ExtractedLicense>) {
+    let license_id = license_ref(short_name.unwrap_or(name).trim_start_matches("LicenseRef-"));
+    let extracted = ExtractedLicense {
+        license_id: license_id.clone(),
+        name: name.to_string(),
+        see_also: see_also.map(|v| v.to_string()),
+        comment: unknown_id.map(|v| {
+            format!(
+                "{} is not an identifier on the SPDX License List {}",
+                v, LICENSE_LIST_VERSION
+            )
+        }),
+    };
+
+    (license_id, Some(extracted))
+}
+
+/// Resolves a single lib.licenses attrset, which only has `spdxId` for licenses on the SPDX
+/// License List.
+fn license_term(license: &License) -> Option<(String, Option<ExtractedLicense>)> {
+    if let Some(id) = license
+        .spdx_id
+        .as_deref()
+        .and_then(|v| canonical_id(v).or_else(|| canonical_exception(v)))
+    {
+        return Some((id.to_string(), None));
+    }
+    if let Some(id) = license.full_name.as_deref().and_then(id_for_name) {
+        return Some((id.to_string(), None));
+    }
+
+    let short_name = license.short_name.as_deref();
+    let name = license
+        .full_name
+        .as_deref()
+        .or(short_name)
+        .or(license.spdx_id.as_deref())?;
+
+    Some(extracted_term(
+        name,
+        short_name.or(license.spdx_id.as_deref()),
+        license.url.as_deref(),
+        license.spdx_id.as_deref(),
+    ))
+}
+
+/// Resolves a bare license name, which older packages use both for SPDX ids and for free form
+/// descriptions.
+fn name_term(name: &str) -> Option<(String, Option<ExtractedLicense>)> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    if let Some(id) = canonical_id(name)
+        .or_else(|| canonical_exception(name))
+        .or_else(|| id_for_name(name))
+    {
+        return Some((id.to_string(), None));
+    }
+
+    let looks_like_id = name.chars().all(is_id_char) && !name.starts_with("LicenseRef-");
+    Some(extracted_term(
+        name,
+        None,
+        None,
+        Some(name).filter(|_| looks_like_id),
+    ))
+}
+
+fn collect_terms(licenses: &Licenses, terms: &mut Vec<(String, Option<ExtractedLicense>)>) {
+    match licenses {
+        Licenses::License(l) => terms.extend(license_term(l)),
+        Licenses::LicenseList(l) => terms.extend(l.iter().flat_map(license_term)),
+        Licenses::NameOnly(l) => terms.extend(name_term(l)),
+        Licenses::NameOnlyList(l) => terms.extend(l.iter().flat_map(|v| name_term(v))),
+        // Lists mixing attrsets and names, possibly nested
+        Licenses::SpecialCase(l) => l.iter().for_each(|v| collect_terms(v, terms)),
+    }
+}
+
+/// Builds the license expression for a package's `meta.license`. nixpkgs lists licenses a package
+/// is available under, so multiple licenses are combined with `OR`.
+pub fn to_expression(licenses: &Licenses) -> Option<LicenseExpression> {
+    let mut terms = Vec::new();
+    collect_terms(licenses, &mut terms);
+
+    let mut ids: Vec<String> = Vec::new();
+    let mut extracted: Vec<ExtractedLicense> = Vec::new();
+    for (id, license) in terms {
+        if ids.contains(&id) {
+            continue;
+        }
+        if canonical_exception(&id).is_some() {
+            // nixpkgs lists an exception next to the license it applies to, as in
+            // `[ asl20 llvm-exception ]`
+            match ids.last_mut().filter(|v| !v.contains(" WITH ")) {
+                Some(license) => license.push_str(&format!(" WITH {}", id)),
+                None => {
+                    let (id, mut license) = extracted_term(&id, None, None, None);
+                    if let Some(license) = &mut license {
+                        license.comment = Some(format!(
+                            "{} is a license exception without a license to apply to",
+                            license.name
+                        ));
+                    }
+                    ids.push(id);
+                    extracted.extend(license);
+                }
+            }
+            continue;
+        }
+        ids.push(id);
+        extracted.extend(license);
+    }
+
+    if ids.is_empty() {
+        return None;
+    }
+
+    Some(LicenseExpression {
+        expression: ids.join(" OR "),
+        extracted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expression(name: &str) -> LicenseExpression {
+        to_expression(&Licenses::NameOnly(name.to_string())).unwrap()
+    }
+
+    #[test]
+    fn license_ref_has_no_operators() {
+        let license = expression("LGPLv2+");
+        assert_eq!(license.expression, "LicenseRef-LGPLv2-or-later");
+        assert_eq!(license.extracted[0].name, "LGPLv2+");
+        assert_eq!(expression("LGPLv2").expression, "LicenseRef-LGPLv2");
+    }
+
+    #[test]
+    fn ids_on_the_license_list_are_kept() {
+        for id in [
+            "OLDAP-2.8",
+            "Qhull",
+            "TCP-wrappers",
+            "Elastic-2.0",
+            "SISSL",
+            "PDDL-1.0",
+        ] {
+            let license = expression(id);
+            assert_eq!(license.expression, id);
+            assert!(license.is_spdx_id());
+        }
+        assert_eq!(expression("qhull").expression, "Qhull");
+        assert_eq!(expression("wxWindows").expression, "wxWindows");
+    }
+
+    #[test]
+    fn exceptions_apply_to_the_preceding_license() {
+        let license = License {
+            full_name: None,
+            short_name: None,
+            spdx_id: None,
+            url: None,
+        };
+        let license = |id: &str| License {
+            spdx_id: Some(id.to_string()),
+            ..license.clone()
+        };
+
+        let licenses =
+            Licenses::LicenseList(vec![license("Apache-2.0"), license("LLVM-exception")]);
+        let expression = to_expression(&licenses).unwrap();
+        assert_eq!(expression.expression, "Apache-2.0 WITH LLVM-exception");
+        assert!(expression.extracted.is_empty());
+
+        let licenses = Licenses::License(license("LLVM-exception"));
+        let expression = to_expression(&licenses).unwrap();
+        assert_eq!(expression.expression, "LicenseRef-LLVM-exception");
+        assert!(expression.extracted[0].comment.is_some());
+    }
+
+    #[test]
+    fn deprecated_or_later_ids_are_replaced() {
+        assert_eq!(expression("GPL-2.0+").expression, "GPL-2.0-or-later");
+    }
+}
@@ -1,313 +1,17 @@
-mod spdx_spec;
-
-extern crate serde;
-#[macro_use]
-extern crate serde_derive;
-extern crate serde_json;
-
-use chrono::prelude::*;
-use clap::{App, Arg};
-use spdx_spec::CreationInfo;
-use spdx_spec::Document;
-use spdx_spec::Package as SPDXPackage;
-use spdx_spec::SpdxSchema;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use nixbom::{
+    findings_json, findings_table, read_components, AdvisoryDb, Cache, Component, DerivationSource,
+    DocumentInfo, Error, Input, Nixpkgs, OutputFormat, PackageIndex, Sbom, SbomBuilder, SbomDiff,
+    SpdxVersion,
+};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
-use std::io::Error;
-use std::path::Path;
-use std::process::Command;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Package {
-    name: String,
-    pname: String,
-    version: String,
-    meta: Meta,
-    #[serde(flatten)]
-    extra: HashMap<String, serde_json::Value>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Meta {
-    license: Option<Licenses>,
-    description: Option<String>,
-    homepage: Option<Homepages>,
-    #[serde(flatten)]
-    extra: HashMap<String, serde_json::Value>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(untagged)]
-enum Homepages {
-    Homepage(String),
-    HomepageList(Vec<String>),
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(untagged)]
-enum Licenses {
-    License(License),
-    LicenseList(Vec<License>),
-    NameOnly(String),
-    NameOnlyList(Vec<String>),
-    SpecialCase(Vec<Licenses>),
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct License {
-    #[serde(rename = "fullName")]
-    full_name: Option<String>,
-    #[serde(rename = "shortName")]
-    short_name: Option<String>,
-    #[serde(rename = "spdxId")]
-    spdx_id: Option<String>,
-    url: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Out {
-    path: String,
-    #[serde(rename = "hashAlgo")]
-    hash_algo: Option<String>,
-    hash: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Drv {
-    outputs: HashMap<String, Out>,
-    #[serde(rename = "inputSrcs")]
-    input_srcs: Vec<String>,
-    #[serde(rename = "inputDrvs")]
-    input_drvs: HashMap<String, Vec<String>>,
-    system: String,
-    builder: String,
-    args: Vec<String>,
-    env: HashMap<String, String>,
-    #[serde(flatten)]
-    extra: HashMap<String, serde_json::Value>,
-}
-
-trait Derivation {
-    fn get_inner_drv(&self) -> Vec<Drv>;
-    fn get_input_derivations(&self) -> Vec<Drv>;
-}
-
-impl Derivation for HashMap<String, Drv> {
-    fn get_inner_drv(&self) -> Vec<Drv> {
-        self.into_iter().map(|(_, v)| v.clone()).collect()
-    }
-
-    fn get_input_derivations(&self) -> Vec<Drv> {
-        self.get_inner_drv()
-            .into_iter()
-            .flat_map(|v| v.input_drvs.clone())
-            .map(|(k, _)| k)
-            .collect::<Vec<String>>()
-            .into_iter()
-            .flat_map(|v| serde_json::from_value(get_derivation_json(&v, false).unwrap()))
-            .collect::<Vec<HashMap<String, Drv>>>()
-            .into_iter()
-            .flat_map(|v| v.get_inner_drv())
-            .collect::<Vec<Drv>>()
-    }
-}
-
-fn get_derivation_json(file: &str, is_expression: bool) -> Result<serde_json::Value, Error> {
-    let output_bytes = get_derivation_bytes(file, is_expression)?;
-    let json = bytes_to_json(output_bytes)?;
-    Ok(json)
-}
-
-fn get_derivation_bytes(file: &str, is_expression: bool) -> Result<Vec<u8>, Error> {
-    let output = if is_expression {
-        Command::new("nix")
-            .arg("show-derivation")
-            .arg("-f")
-            .arg(file)
-            .output()
-    } else {
-        Command::new("nix")
-            .arg("show-derivation")
-            .arg(file)
-            .output()
-    }?;
-
-    Ok(output.stdout)
-}
-
-fn bytes_to_json(bytes: Vec<u8>) -> serde_json::Result<serde_json::Value> {
-    let v = serde_json::from_slice(&bytes)?;
-
-    Ok(v)
-}
-
-fn get_packages_wrapper(with_cache: bool) -> Result<HashMap<String, Package>, Error> {
-    if with_cache {
-        Ok(package_fixer(get_packages_cached("nixpkgs.json")?)) // TODO: This shouldn't be hardcoded and be configurable
-    } else {
-        Ok(package_fixer(get_packages()?))
-    }
-}
-
-fn package_fixer(packages: HashMap<String, Package>) -> HashMap<String, Package> {
-    packages
-    .into_iter()
-    .map(|(_, v)| (v.name.clone(), v))
-    .collect::<HashMap<String, Package>>()
-}
-
-fn get_packages() -> serde_json::Result<HashMap<String, Package>> {
-    let output = Command::new("nix-env")
-        .arg("-qa")
-        .arg(".*")
-        .arg("--json")
-        .arg("--meta")
-        .output()
-        .unwrap();
-
-    // TODO: Below shouldn't live in here and should be cleaned up. Should be configurable and not hard coded.
-    match File::create("nixpkgs.json")
-            .and_then(|mut f| f.write_all(&output.stdout)) {
-        Ok(_) => {},
-        Err(e) => eprintln!("{}", e),
-    }
-
-    serde_json::from_slice(&output.stdout)
-}
-
-fn get_packages_cached(path: &str) -> Result<HashMap<String, Package>, Error> {
-    let path = Path::new(&path);
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-
-    let packages: HashMap<String, Package> = serde_json::from_reader(reader)?;
-    let mapped = packages
-        .into_iter()
-        .map(|(_, v)| (v.name.clone(), v))
-        .collect::<HashMap<String, Package>>();
-
-    Ok(mapped)
-}
-
-trait SpdxPackages {
-    fn get_spdx_package_info_if_exists(&self, package: String) -> Option<SPDXPackage>;
-}
-
-fn license_helper(license: License) -> Option<String> {
-    license
-        .spdx_id
-        .or(license.full_name.or(license.short_name.or(license.url)))
-}
-
-impl SpdxPackages for HashMap<String, Package> {
-    fn get_spdx_package_info_if_exists(&self, package_name: String) -> Option<SPDXPackage> {
-        let package = self.get(&package_name)?;
-        let license = package
-            .meta
-            .license
-            .to_owned()
-            .map(|v| match v {
-                Licenses::License(l) => vec![license_helper(l)],
-                Licenses::LicenseList(l) => l.into_iter().map(|v| license_helper(v)).collect(),
-                Licenses::NameOnly(l) => vec![Some(l)],
-                Licenses::NameOnlyList(l) => l.into_iter().map(|v| Some(v)).collect(),
-                Licenses::SpecialCase(_) => vec![None], // TODO: Figure out what todo with special cases!
-            })
-            .into_iter()
-            .flatten()
-            .collect();
-
-        let homepage = {
-            package.meta.homepage.to_owned().map(|v| match v {
-                Homepages::Homepage(h) => h,
-                // Assume if a list of URLs pick the first one
-                Homepages::HomepageList(h) => h[0].clone(),
-            })
-        };
-        let s = SPDXPackage {
-            annotations: None,
-            attribution_texts: None,
-            checksums: None,
-            comment: None,
-            copyright_text: None,
-            description: package.meta.description.to_owned(),
-            download_location: None,
-            external_refs: None,
-            files_analyzed: None,
-            has_files: None,
-            homepage: homepage,
-            license_comments: None,
-            license_info_from_files: license,
-            name: None,
-            originator: None,
-            package_file_name: None,
-            package_verification_code: None,
-            source_info: None,
-            summary: None,
-            supplier: None,
-            version_info: Some(package.version.to_owned()),
-        };
-
-        Some(s)
-    }
-}
-
-impl SpdxSchema {
-    fn new(
-        name: String,
-        created: String,
-        creators: Vec<String>,
-        data_license: String,
-        materials: Vec<String>,
-        package_data: HashMap<String, Package>,
-    ) -> SpdxSchema {
-        let creation_info = CreationInfo {
-            comment: None,
-            created: Some(created),
-            creators: Some(creators),
-            license_list_version: None,
-        };
-
-        let packages = materials
-            .into_iter()
-            .flat_map(|v| package_data.get_spdx_package_info_if_exists(v))
-            .collect::<Vec<spdx_spec::Package>>();
-
-        let document = Document {
-            annotations: None,
-            comment: None,
-            creation_info: Some(creation_info),
-            data_license: Some(data_license),
-            describes_packages: None,
-            external_document_refs: None,
-            files: None, // TODO: Support file based SBOMs
-            has_extracted_licensing_infos: None,
-            name: Some(name),
-            packages: Some(packages),
-            relationships: None,
-            revieweds: None,
-            snippets: None,
-            spdx_version: Some("SPDX-2.2".to_string()), // TODO: Support multiple SPDX versions
-        };
-
-        SpdxSchema {
-            document: Some(document),
-        }
-    }
-}
+use std::path::{Path, PathBuf};
+use std::process;
 
-fn main() -> Result<(), Error> {
+fn main() {
     let matches = App::new("SPDNix")
         .version("0.1")
         .author("Michael Lieberman and Jack Kelly")
-        .arg(
-            Arg::with_name("DERIVATION")
-                .help("Sets the derivation to generate a SPDX Software Bill of Materials for")
-                .required(true)
-                .index(1),
-        )
         .arg(
             Arg::with_name("name")
                 .help("Name of the project you are building SBOM for.")
@@ -333,38 +37,360 @@ fn main() -> Result<(), Error> {
                 .default_value("CC0-1.0"), // TODO: Make part of configuration?
         )
         .arg(
-            Arg::with_name("with_cache")
-                .help("Whether or not to use a Nix package cache json.")
-                .long("--with_cache")
-                .required(false)
+            Arg::with_name("format")
+                .help("Output format of the SBOM")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&[
+                    "spdx-json",
+                    "spdx-tag-value",
+                    "cyclonedx-json",
+                    "cyclonedx-xml",
+                ])
+                .default_value("spdx-json"),
+        )
+        .arg(
+            Arg::with_name("cyclonedx_version")
+                .help("CycloneDX specification version of the generated BOM")
+                .long("cyclonedx-version")
+                .takes_value(true)
+                .possible_values(&["1.4", "1.5"])
+                .default_value("1.5"),
+        )
+        .arg(
+            Arg::with_name("spdx_version")
+                .help("SPDX specification version of the generated document")
+                .long("spdx-version")
+                .takes_value(true)
+                .possible_values(&["2.2", "2.3"])
+                .default_value("2.3"),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .help("Base URI of the document namespace. The document name and the root derivation hash are appended to it.")
+                .long("namespace")
+                .takes_value(true)
+                .default_value("https://spdx.org/spdxdocs"),
+        )
+        .arg(
+            Arg::with_name("nar_hash")
+                .help("Add the NAR hash of realised outputs as checksum of packages that aren't fixed-output derivations.")
+                .long("nar-hash")
                 .takes_value(false),
         )
+        .arg(derivation_arg())
+        .args(&sbom_args())
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("scan")
+                .about("Matches the packages of DERIVATION against local vulnerability databases")
+                .arg(derivation_arg())
+                .args(&sbom_args())
+                .arg(
+                    Arg::with_name("osv")
+                        .help("OSV advisory file, or a directory of them like an unpacked OSV dump")
+                        .long("osv")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("nvd")
+                        .help("NVD API 2.0 response, e.g. a saved page of /rest/json/cves/2.0, or legacy JSON 1.1 data feed, or a directory of them")
+                        .long("nvd")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .help("Output format of the findings")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["table", "json"])
+                        .default_value("table"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Compares two SBOMs, each either generated for a derivation or read from an SPDX or CycloneDX JSON document")
+                .arg(
+                    Arg::with_name("OLD")
+                        .help("SPDX or CycloneDX JSON file, or anything DERIVATION accepts")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("NEW")
+                        .help("SPDX or CycloneDX JSON file, or anything DERIVATION accepts")
+                        .required(true)
+                        .index(2),
+                )
+                .args(&sbom_args())
+                .arg(
+                    Arg::with_name("nar_hash")
+                        .help("Add the NAR hash of realised outputs as checksum of packages that aren't fixed-output derivations.")
+                        .long("nar-hash")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .help("Output format of the differences")
+                        .short("f")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text"),
+                ),
+        )
         .get_matches();
 
-    // TODO: Handle failure better
-    let derivation = matches.value_of("DERIVATION").unwrap();
-    let json = get_derivation_json(derivation, true)?;
-    let derivation_struct_map: HashMap<String, Drv> = serde_json::from_value(json)?;
-    let input_derivations = derivation_struct_map.get_input_derivations();
+    let result = match matches.subcommand() {
+        ("scan", Some(matches)) => scan(matches),
+        ("diff", Some(matches)) => diff(matches),
+        _ => run(&matches),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn derivation_arg() -> Arg<'static, 'static> {
+    Arg::with_name("DERIVATION")
+        .help("Nix expression file, flake reference (e.g. .#default), .drv path or output store path to generate a Software Bill of Materials for")
+        .required(true)
+        .index(1)
+}
+
+/// Arguments selecting the derivation graph and the nixpkgs metadata, shared by generating,
+/// scanning and comparing SBOMs.
+fn sbom_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("depth")
+            .help("How many levels of input derivations to include. Defaults to the whole closure.")
+            .short("d")
+            .long("depth")
+            .takes_value(true)
+            .validator(|v| {
+                v.parse::<usize>()
+                    .map(|_| ())
+                    .map_err(|_| "depth must be a non-negative number".to_string())
+            }),
+        Arg::with_name("native")
+            .help("Parse .drv files directly instead of calling `nix show-derivation`. DERIVATION has to be a .drv or output store path.")
+            .long("native")
+            .takes_value(false),
+        Arg::with_name("store_root")
+            .help("Directory store paths are resolved against, e.g. a copied store snapshot. Implies --native.")
+            .long("store-root")
+            .takes_value(true),
+        Arg::with_name("runtime")
+            .help("Only include what the realised outputs reference at runtime, following store path references instead of input derivations. DERIVATION has to be built.")
+            .long("runtime")
+            .takes_value(false),
+        Arg::with_name("only_nixpkgs")
+            .help("Only include derivations that could be matched with nixpkgs metadata.")
+            .long("only-nixpkgs")
+            .takes_value(false),
+        Arg::with_name("report_unmatched")
+            .help("Print the derivations that couldn't be matched with nixpkgs metadata to stderr.")
+            .long("report-unmatched")
+            .takes_value(false),
+        Arg::with_name("nixpkgs")
+            .help("nixpkgs path or flake reference to evaluate package metadata from. Defaults to the nixpkgs locked in the flake.lock next to DERIVATION, or <nixpkgs> from NIX_PATH.")
+            .long("nixpkgs")
+            .takes_value(true),
+        Arg::with_name("metadata")
+            .help("Which nixpkgs packages to query metadata of. `closure` only evaluates the attributes the derivations appear to come from, which is much faster but may match fewer of them. `none` describes packages by their derivations alone and needs no nix. The cache is only used for `all`.")
+            .long("metadata")
+            .takes_value(true)
+            .possible_values(&["all", "closure", "none"])
+            .default_value("all"),
+        Arg::with_name("metadata_file")
+            .help("Read package metadata from a file holding `nix-env -qa --json --meta --drv-path --out-path` output instead of querying nixpkgs, which needs no nix.")
+            .long("metadata-file")
+            .takes_value(true)
+            .conflicts_with_all(&["nixpkgs", "cache_path", "refresh_cache"]),
+        Arg::with_name("cache_path")
+            .help("Directory nixpkgs metadata is cached in. Defaults to $XDG_CACHE_HOME/nixbom.")
+            .long("cache-path")
+            .takes_value(true),
+        Arg::with_name("refresh_cache")
+            .help("Query nixpkgs metadata again even if the cache has it, and update the cache.")
+            .long("refresh-cache")
+            .takes_value(false),
+        Arg::with_name("no_cache")
+            .help("Neither read nor write the nixpkgs metadata cache.")
+            .long("no-cache")
+            .takes_value(false)
+            .conflicts_with_all(&["cache_path", "refresh_cache"]),
+        Arg::with_name("with_cache")
+            .help("Deprecated, same as --metadata-file nixpkgs.json.")
+            .long("--with_cache")
+            .hidden(true)
+            .takes_value(false),
+    ]
+}
+
+/// Returns the value of an argument that is either required or has a default.
+fn value<'a>(matches: &'a ArgMatches, name: &str) -> Result<&'a str, Error> {
+    matches
+        .value_of(name)
+        .ok_or_else(|| Error::InvalidInput(format!("missing value for {}", name)))
+}
+
+/// Returns the nixpkgs to evaluate metadata from, warning when it's only a guess.
+fn nixpkgs(matches: &ArgMatches, derivation: &str) -> Result<Nixpkgs, Error> {
+    if let Some(v) = matches.value_of("nixpkgs") {
+        return Ok(Nixpkgs::from_arg(v));
+    }
+
+    let nixpkgs = Nixpkgs::detect(&Input::detect(derivation))?;
+    if let Nixpkgs::NixPath = nixpkgs {
+        eprintln!(
+            "warning: couldn't tell which nixpkgs {} was built from, package metadata \
+             comes from <nixpkgs> in NIX_PATH and may not match, pass --nixpkgs to choose it",
+            derivation
+        );
+    }
+
+    Ok(nixpkgs)
+}
 
-    let packages = get_packages_wrapper(matches.is_present("with_cache"))?;
-    let sbom = SpdxSchema::new(
-        matches.value_of("name").unwrap().to_string(),
-        Utc::now().to_string(),
-        matches
-            .values_of("authors")
-            .unwrap()
-            .map(|v| v.to_string())
-            .collect(), // TODO: Maybe even pull info from git config if exists?
-        matches.value_of("data_license").unwrap().to_string(),
-        input_derivations
-            .into_iter()
-            .map(|v| v.env["name"].clone())
-            .collect(),
-        packages,
+fn load_sbom(matches: &ArgMatches, derivation: &str) -> Result<Sbom, Error> {
+    let source = match matches.value_of("store_root") {
+        Some(root) => DerivationSource::Store(PathBuf::from(root)),
+        None if matches.is_present("native") => DerivationSource::Store(PathBuf::from("/")),
+        None => DerivationSource::Nix,
+    };
+    let depth = matches
+        .value_of("depth")
+        .map(|v| v.parse::<usize>())
+        .transpose()
+        .map_err(|e| Error::InvalidInput(format!("invalid depth: {}", e)))?;
+    let graph = SbomBuilder::new(derivation)
+        .source(source)
+        .depth(depth)
+        .nar_hashes(matches.is_present("nar_hash"))
+        .runtime(matches.is_present("runtime"))
+        .load()?;
+
+    let metadata_file = match matches.value_of("metadata_file") {
+        Some(path) => Some(path),
+        None if matches.is_present("with_cache") => Some("nixpkgs.json"),
+        None => None,
+    };
+    let package_index = match (metadata_file, value(matches, "metadata")?) {
+        (Some(path), _) => PackageIndex::from_file(Path::new(path))?,
+        (None, "none") => PackageIndex::new(HashMap::new()),
+        (None, "closure") => PackageIndex::evaluate(&nixpkgs(matches, derivation)?, &graph)?,
+        (None, _) => {
+            let cache = if matches.is_present("no_cache") {
+                None
+            } else {
+                let cache = Cache::new(matches.value_of("cache_path").map(PathBuf::from))?;
+                Some(cache.refresh(matches.is_present("refresh_cache")))
+            };
+            PackageIndex::load(&nixpkgs(matches, derivation)?, cache.as_ref())?
+        }
+    };
+    for warning in package_index.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    let mut sbom = graph.enrich(package_index);
+    if matches.is_present("only_nixpkgs") {
+        sbom.retain_nixpkgs();
+    }
+    if matches.is_present("report_unmatched") {
+        let unmatched = sbom.unmatched();
+
+        eprintln!(
+            "{} of {} derivations have no nixpkgs metadata:",
+            unmatched.len(),
+            sbom.graph.derivations.len()
+        );
+        for path in unmatched {
+            eprintln!("  {}", path);
+        }
+    }
+
+    Ok(sbom)
+}
+
+fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let sbom = load_sbom(matches, value(matches, "DERIVATION")?)?;
+    let format = value(matches, "format")?
+        .parse::<OutputFormat>()
+        .map_err(Error::InvalidInput)?;
+    let spdx_version = value(matches, "spdx_version")?
+        .parse::<SpdxVersion>()
+        .map_err(Error::InvalidInput)?;
+
+    let authors = matches.values_of("authors").into_iter().flatten(); // TODO: Maybe even pull info from git config if exists?
+    let mut info = DocumentInfo::new(
+        value(matches, "name")?,
+        authors.map(|v| v.to_string()).collect(),
     );
+    info.data_license = value(matches, "data_license")?.to_string();
+    info.namespace = value(matches, "namespace")?.to_string();
+    info.spdx_version = spdx_version;
+    info.cyclonedx_version = value(matches, "cyclonedx_version")?.to_string();
+
+    println!("{}", sbom.render(&info, format)?);
+
+    Ok(())
+}
+
+fn scan(matches: &ArgMatches) -> Result<(), Error> {
+    let mut db = AdvisoryDb::new();
+    for path in matches.values_of("osv").into_iter().flatten() {
+        db.load_osv(Path::new(path))?;
+    }
+    for path in matches.values_of("nvd").into_iter().flatten() {
+        db.load_nvd(Path::new(path))?;
+    }
 
-    println!("{}", serde_json::to_string_pretty(&sbom)?);
+    let findings = load_sbom(matches, value(matches, "DERIVATION")?)?.scan(&db);
+    match value(matches, "format")? {
+        "json" => println!("{}", findings_json(&findings)?),
+        _ => println!("{}", findings_table(&findings)),
+    }
+
+    Ok(())
+}
+
+/// Reads the packages of an SBOM: `path` is a JSON document if it names one, a derivation
+/// otherwise.
+fn components(matches: &ArgMatches, path: &str) -> Result<Vec<Component>, Error> {
+    let file = Path::new(path);
+    let extension = file
+        .extension()
+        .and_then(|v| v.to_str())
+        .unwrap_or_default();
+    if file.is_file() && extension == "json" {
+        read_components(file)
+    } else if file.is_file() && ["spdx", "xml"].contains(&extension) {
+        Err(Error::InvalidInput(format!(
+            "{} isn't a supported document, diff reads SPDX JSON and CycloneDX JSON",
+            path
+        )))
+    } else {
+        Ok(load_sbom(matches, path)?.components())
+    }
+}
+
+fn diff(matches: &ArgMatches) -> Result<(), Error> {
+    let old = components(matches, value(matches, "OLD")?)?;
+    let new = components(matches, value(matches, "NEW")?)?;
+
+    let diff = SbomDiff::new(&old, &new);
+    match value(matches, "format")? {
+        "json" => println!("{}", diff.to_json()?),
+        _ => println!("{}", diff.to_text()),
+    }
 
     Ok(())
 }
@@ -1 +0,0 @@
-pub mod spdx_spec;
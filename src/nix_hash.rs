@@ -0,0 +1,197 @@
+// Conversions between the hash encodings Nix uses (base16, nix32 and SRI/base64) and the
+// lowercase hex SPDX and CycloneDX expect.
+
+const NIX32_CHARS: &[u8] = b"0123456789abcdfghijklmnpqrsvwxyz";
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Size in bytes of the digest of a hash algorithm Nix supports.
+fn digest_size(algorithm: &str) -> Option<usize> {
+    match algorithm {
+        "md5" => Some(16),
+        "sha1" => Some(20),
+        "sha256" => Some(32),
+        "sha512" => Some(64),
+        _ => None,
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes Nix's base32 variant, which uses its own alphabet and reads the string back to front.
+fn nix32_decode(s: &str, size: usize) -> Option<Vec<u8>> {
+    let mut bytes = vec![0u8; size];
+
+    for (n, c) in s.bytes().rev().enumerate() {
+        let digit = NIX32_CHARS.iter().position(|v| *v == c)? as u16;
+        let b = n * 5;
+        let (i, j) = (b / 8, b % 8);
+        bytes[i] |= (digit << j) as u8;
+        let carry = (digit << j) >> 8;
+        if i + 1 < size {
+            bytes[i + 1] |= carry as u8;
+        } else if carry != 0 {
+            return None;
+        }
+    }
+
+    Some(bytes)
+}
+
+fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in s.trim_end_matches('=').bytes() {
+        let value = BASE64_CHARS.iter().position(|v| *v == c)? as u32;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+/// Converts a hash in any of the encodings Nix accepts into lowercase hex. `hash` may carry its
+/// own algorithm prefix (`sha256:…` or `sha256-…`), otherwise `algorithm` is used.
+pub fn hash_to_hex(algorithm: &str, hash: &str) -> Option<String> {
+    let (algorithm, hash, is_sri) = match hash.split_once([':', '-']) {
+        Some((prefix, rest)) if digest_size(prefix).is_some() => {
+            (prefix, rest, hash.as_bytes()[prefix.len()] == b'-')
+        }
+        _ => (algorithm, hash, false),
+    };
+    let size = digest_size(algorithm)?;
+
+    if is_sri {
+        return base64_decode(hash)
+            .filter(|v| v.len() == size)
+            .map(|v| to_hex(&v));
+    }
+
+    if hash.len() == size * 2 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(hash.to_ascii_lowercase())
+    } else if hash.len() == (size * 8).div_ceil(5) {
+        nix32_decode(hash, size).map(|v| to_hex(&v))
+    } else if hash.len() == size.div_ceil(3) * 4 {
+        base64_decode(hash)
+            .filter(|v| v.len() == size)
+            .map(|v| to_hex(&v))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Digests of the empty string
+    const SHA256_HEX: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const SHA256_NIX32: &str = "0mdqa9w1p6cmli6976v4wi0sw9r4p5prkj7lzfd1877wk11c9c73";
+    const SHA256_BASE64: &str = "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=";
+
+    fn hex(algorithm: &str, hash: &str) -> Option<String> {
+        hash_to_hex(algorithm, hash)
+    }
+
+    #[test]
+    fn decodes_sha256_in_every_encoding() {
+        for hash in [
+            SHA256_HEX.to_string(),
+            SHA256_HEX.to_uppercase(),
+            SHA256_NIX32.to_string(),
+            SHA256_BASE64.to_string(),
+            format!("sha256:{}", SHA256_HEX),
+            format!("sha256:{}", SHA256_NIX32),
+            format!("sha256-{}", SHA256_BASE64),
+        ] {
+            assert_eq!(
+                hex("sha256", &hash).as_deref(),
+                Some(SHA256_HEX),
+                "{}",
+                hash
+            );
+        }
+    }
+
+    #[test]
+    fn prefixes_override_the_algorithm() {
+        assert_eq!(
+            hex("md5", &format!("sha256-{}", SHA256_BASE64)).as_deref(),
+            Some(SHA256_HEX)
+        );
+    }
+
+    #[test]
+    fn decodes_other_algorithms() {
+        assert_eq!(
+            hex("md5", "3y8bwfr609h3lh9ch0izcqq7fl").as_deref(),
+            Some("d41d8cd98f00b204e9800998ecf8427e")
+        );
+        assert_eq!(
+            hex("sha1", "143xibwh31h9bvxzalr0sjvbbvpa6ffs").as_deref(),
+            Some("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+        );
+        assert_eq!(
+            hex("sha1", "sha1-2jmj7l5rSw0yVb/vlWAYkK/YBwk=").as_deref(),
+            Some("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+        );
+        assert_eq!(
+            hex(
+                "sha512",
+                "sha512-z4PhNX7vuL3xVChQ1m2AB9Yg5AULVxXcg/SpIdNs6c5H0NE8XYXysP+DGNKHfuwvY7kxvUdBeoGlODJ6+SfaPg=="
+            )
+            .as_deref(),
+            Some("cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e")
+        );
+        assert_eq!(
+            hex(
+                "sha256",
+                "094qif9n4cq4fdg459qzbhg1c6wywawwaaivx0k0x8xhbyx4vwic"
+            )
+            .as_deref(),
+            Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+        );
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        assert_eq!(hex("sha256", &SHA256_HEX[1..]), None);
+        assert_eq!(hex("sha256", &format!("{}0", SHA256_NIX32)), None);
+        assert_eq!(hex("sha1", SHA256_HEX), None);
+        assert_eq!(hex("sha256", "sha256-1B2M2Y8AsgTpgAmY7PhCfg=="), None);
+        assert_eq!(hex("sha256", ""), None);
+    }
+
+    #[test]
+    fn rejects_bad_characters() {
+        // e, o, u and t aren't part of the nix32 alphabet
+        assert_eq!(hex("sha256", &SHA256_NIX32.replace('m', "e")), None);
+        assert_eq!(hex("sha256", &SHA256_HEX.replace('e', "g")), None);
+        assert_eq!(hex("sha256", &SHA256_BASE64.replace('+', "-")), None);
+        assert_eq!(
+            hex(
+                "sha256",
+                &format!("sha256-{}", SHA256_BASE64.replace('/', "_"))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_nix32_overflowing_the_digest() {
+        // 52 characters hold 260 bits, the leading one may only use the low 4 of its 5
+        assert_eq!(hex("sha256", &format!("z{}", &SHA256_NIX32[1..])), None);
+    }
+
+    #[test]
+    fn rejects_unknown_algorithms() {
+        assert_eq!(hex("blake3", SHA256_HEX), None);
+    }
+}
@@ -0,0 +1,581 @@
+// nixpkgs package metadata as reported by `nix-env -qa --json --meta`, and the lookup from
+// derivations into it.
+
+use crate::cache::{Cache, NixpkgsId};
+use crate::derivation::{store_path_hash, DerivationGraph, Drv};
+use crate::error::{command_output, Error};
+use crate::fetcher::is_fixed_output;
+use crate::flake_lock::{locked_nixpkgs, locked_reference, remote_locked_nixpkgs, LockedInput};
+use crate::input::{store_dir, Input, FLAKE_SCHEMES};
+use crate::license::{self, LicenseExpression};
+use chrono::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+/// Evaluates the metadata of a list of attributes, see `PackageIndex::evaluate`.
+const CLOSURE_META: &str = include_str!("closure_meta.nix");
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub pname: String,
+    pub version: String,
+    #[serde(rename = "drvPath")]
+    pub drv_path: Option<String>,
+    pub outputs: Option<HashMap<String, Option<String>>>,
+    pub meta: Meta,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Meta {
+    pub license: Option<Licenses>,
+    pub description: Option<String>,
+    pub homepage: Option<Homepages>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Homepages {
+    Homepage(String),
+    HomepageList(Vec<String>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Licenses {
+    License(License),
+    LicenseList(Vec<License>),
+    NameOnly(String),
+    NameOnlyList(Vec<String>),
+    SpecialCase(Vec<Licenses>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct License {
+    #[serde(rename = "fullName")]
+    pub full_name: Option<String>,
+    #[serde(rename = "shortName")]
+    pub short_name: Option<String>,
+    #[serde(rename = "spdxId")]
+    pub spdx_id: Option<String>,
+    pub url: Option<String>,
+}
+
+/// The nixpkgs package metadata is evaluated from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nixpkgs {
+    /// `<nixpkgs>` from `NIX_PATH`, i.e. whatever `nix-env` evaluates by default.
+    NixPath,
+    /// A nixpkgs checkout or store path.
+    Path(PathBuf),
+    /// A flake reference, e.g. `github:NixOS/nixpkgs/<rev>`. `nar_hash` is known for locked
+    /// inputs and saves fetching the flake when its metadata is cached.
+    Flake {
+        reference: String,
+        nar_hash: Option<String>,
+    },
+}
+
+impl Nixpkgs {
+    /// Parses a `--nixpkgs` argument, an existing path or otherwise a flake reference.
+    pub fn from_arg(arg: &str) -> Nixpkgs {
+        if !FLAKE_SCHEMES.iter().any(|v| arg.starts_with(v)) && Path::new(arg).exists() {
+            Nixpkgs::Path(PathBuf::from(arg))
+        } else {
+            Nixpkgs::Flake {
+                reference: arg.to_string(),
+                nar_hash: None,
+            }
+        }
+    }
+
+    /// Picks the nixpkgs `input` was built from: the one locked in the flake.lock of a flake,
+    /// local or remote, or of the directory an expression file is in. A remote flake that is
+    /// nixpkgs itself is its own nixpkgs. Store paths don't tell, and neither does a lock
+    /// without a `nixpkgs` input, so those fall back to `NIX_PATH`.
+    pub fn detect(input: &Input) -> Result<Nixpkgs, Error> {
+        let dir = match input {
+            Input::Flake(v) => {
+                let path = v.split('#').next().unwrap_or_default();
+                let path = path.strip_prefix("path:").unwrap_or(path);
+                if FLAKE_SCHEMES.iter().any(|v| path.starts_with(v)) {
+                    return Ok(Nixpkgs::from_locked(remote_locked_nixpkgs(path)?));
+                }
+                PathBuf::from(if path.is_empty() { "." } else { path })
+            }
+            Input::Expression(v) => match Path::new(v).parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            },
+            Input::DrvPath(_) | Input::OutputPath(_) => return Ok(Nixpkgs::NixPath),
+        };
+
+        Ok(Nixpkgs::from_locked(locked_nixpkgs(&dir)?))
+    }
+
+    fn from_locked(locked: Option<LockedInput>) -> Nixpkgs {
+        match locked {
+            Some(locked) => Nixpkgs::Flake {
+                reference: locked.reference,
+                nar_hash: locked.nar_hash,
+            },
+            None => Nixpkgs::NixPath,
+        }
+    }
+
+    /// Identifies this nixpkgs for caching and for recording in the generated document.
+    pub fn id(&self) -> Result<NixpkgsId, Error> {
+        Ok(self.identify()?.0)
+    }
+
+    /// Like `id`, also returning the flake if identifying it meant fetching it, for
+    /// `expression_dir` to reuse.
+    fn identify(&self) -> Result<(NixpkgsId, Option<PrefetchedFlake>), Error> {
+        match self {
+            Nixpkgs::NixPath => {
+                let output = command_output(
+                    Command::new("nix-instantiate")
+                        .arg("--find-file")
+                        .arg("nixpkgs"),
+                )?;
+                Ok((
+                    path_id(Path::new(String::from_utf8_lossy(&output).trim()))?,
+                    None,
+                ))
+            }
+            Nixpkgs::Path(path) => Ok((path_id(path)?, None)),
+            Nixpkgs::Flake {
+                reference,
+                nar_hash: Some(nar_hash),
+            } => Ok((
+                NixpkgsId {
+                    key: nar_hash.clone(),
+                    fingerprint: nar_hash.clone(),
+                    source: reference.clone(),
+                },
+                None,
+            )),
+            Nixpkgs::Flake {
+                reference,
+                nar_hash: None,
+            } => {
+                // Record the revision a branch or registry name resolved to, not the name
+                let prefetched = prefetch_flake(reference)?;
+                let id = NixpkgsId {
+                    source: locked_reference(&prefetched.locked)
+                        .unwrap_or_else(|| reference.clone()),
+                    key: prefetched.hash.clone(),
+                    fingerprint: prefetched.hash.clone(),
+                };
+                Ok((id, Some(prefetched)))
+            }
+        }
+    }
+
+    /// The directory to point `nix-env -f` at, `None` for `NIX_PATH`. A flake is fetched unless
+    /// `prefetched` already holds it.
+    fn expression_dir(
+        &self,
+        prefetched: Option<PrefetchedFlake>,
+    ) -> Result<Option<PathBuf>, Error> {
+        match self {
+            Nixpkgs::NixPath => Ok(None),
+            Nixpkgs::Path(path) => Ok(Some(path.clone())),
+            Nixpkgs::Flake { reference, .. } => {
+                let prefetched = match prefetched {
+                    Some(prefetched) => prefetched,
+                    None => prefetch_flake(reference)?,
+                };
+                Ok(Some(PathBuf::from(prefetched.store_path)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Nixpkgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Nixpkgs::NixPath => write!(f, "<nixpkgs>"),
+            Nixpkgs::Path(path) => write!(f, "{}", path.display()),
+            Nixpkgs::Flake { reference, .. } => write!(f, "{}", reference),
+        }
+    }
+}
+
+/// The parts of `nix flake prefetch --json` we use.
+#[derive(Debug, Deserialize)]
+struct PrefetchedFlake {
+    hash: String,
+    #[serde(rename = "storePath")]
+    store_path: String,
+    #[serde(default)]
+    locked: serde_json::Value,
+}
+
+/// Fetches a flake into the store.
+fn prefetch_flake(reference: &str) -> Result<PrefetchedFlake, Error> {
+    let output = command_output(
+        Command::new("nix")
+            .arg("--extra-experimental-features")
+            .arg("nix-command flakes")
+            .arg("flake")
+            .arg("prefetch")
+            .arg("--json")
+            .arg(reference),
+    )?;
+
+    serde_json::from_slice(&output)
+        .map_err(|e| Error::json(format!("`nix flake prefetch` for {}", reference), e))
+}
+
+/// Lookup tables from derivations into the nixpkgs metadata, which is keyed by attribute path.
+pub struct PackageIndex {
+    packages: HashMap<String, Package>,
+    by_drv_path: HashMap<String, String>,
+    by_out_path: HashMap<String, String>,
+    by_name: HashMap<String, String>,
+    source: Option<String>,
+    warnings: Vec<Error>,
+}
+
+impl PackageIndex {
+    /// Loads the metadata of every package in `nixpkgs`, from `cache` if it has an up to date
+    /// entry for that nixpkgs. Without a cache nixpkgs is only identified by how it was given,
+    /// which saves resolving it.
+    pub fn load(nixpkgs: &Nixpkgs, cache: Option<&Cache>) -> Result<PackageIndex, Error> {
+        let cache = match cache {
+            Some(cache) => cache,
+            None => {
+                let data = query_packages(nixpkgs.expression_dir(None)?.as_deref())?;
+                let mut index = PackageIndex::new(parse_packages(&data, None)?);
+                index.source = Some(nixpkgs.to_string());
+                return Ok(index);
+            }
+        };
+
+        let (id, prefetched) = nixpkgs.identify()?;
+        let with_source = |packages| {
+            let mut index = PackageIndex::new(packages);
+            index.source = Some(id.source.clone());
+            index
+        };
+
+        if let Some(data) = cache.get(&id)? {
+            return Ok(with_source(parse_packages(&data, Some(cache))?));
+        }
+
+        let data = query_packages(nixpkgs.expression_dir(prefetched)?.as_deref())?;
+        let created = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let mut index = with_source(parse_packages(&data, None)?);
+        if let Err(e) = cache.put(&id, &data, &created) {
+            // Not being able to cache shouldn't keep anyone from getting their SBOM
+            index.warnings.push(e);
+        }
+
+        Ok(index)
+    }
+
+    /// Evaluates the metadata of only the packages in `graph` instead of all of nixpkgs, which
+    /// takes seconds rather than minutes. Attribute paths are guessed from package names, so
+    /// packages whose attribute is named differently stay unmatched.
+    pub fn evaluate(nixpkgs: &Nixpkgs, graph: &DerivationGraph) -> Result<PackageIndex, Error> {
+        let (id, prefetched) = nixpkgs.identify()?;
+        let attrs = serde_json::to_string(&attribute_candidates(graph))
+            .map_err(|e| Error::json("the attribute list", e))?;
+
+        let mut command = Command::new("nix-instantiate");
+        command
+            .arg("--eval")
+            .arg("--strict")
+            .arg("--json")
+            .arg("--expr")
+            .arg(CLOSURE_META)
+            .arg("--argstr")
+            .arg("attrs")
+            .arg(attrs);
+        if let Some(dir) = nixpkgs.expression_dir(prefetched)? {
+            command.arg("--argstr").arg("nixpkgs").arg(dir);
+        }
+
+        let output = command_output(&mut command)?;
+        let packages = serde_json::from_slice(&output)
+            .map_err(|e| Error::json("the evaluation of the closure's metadata", e))?;
+
+        let mut index = PackageIndex::new(packages);
+        index.source = Some(id.source);
+        Ok(index)
+    }
+
+    /// Loads package metadata from a file holding `nix-env -qa --json --meta` output.
+    pub fn from_file(path: &Path) -> Result<PackageIndex, Error> {
+        let file =
+            File::open(path).map_err(|e| Error::io(format!("opening {}", path.display()), e))?;
+        let reader = BufReader::new(file);
+
+        let packages: HashMap<String, Package> = serde_json::from_reader(reader)
+            .map_err(|e| Error::json(path.display().to_string(), e))?;
+
+        Ok(PackageIndex::new(packages))
+    }
+
+    pub fn new(packages: HashMap<String, Package>) -> PackageIndex {
+        let mut by_drv_path = HashMap::new();
+        let mut by_out_path = HashMap::new();
+        let mut by_name = HashMap::new();
+
+        // Sorted so that name collisions always resolve to the same attribute path
+        let mut attribute_paths = packages.keys().collect::<Vec<&String>>();
+        attribute_paths.sort();
+
+        for attribute_path in attribute_paths {
+            let package = &packages[attribute_path];
+            if let Some(drv_path) = &package.drv_path {
+                by_drv_path
+                    .entry(drv_path.clone())
+                    .or_insert_with(|| attribute_path.clone());
+            }
+            for out_path in package.outputs.iter().flat_map(|v| v.values().flatten()) {
+                by_out_path
+                    .entry(out_path.clone())
+                    .or_insert_with(|| attribute_path.clone());
+            }
+            by_name
+                .entry(package.name.clone())
+                .or_insert_with(|| attribute_path.clone());
+        }
+
+        PackageIndex {
+            packages,
+            by_drv_path,
+            by_out_path,
+            by_name,
+            source: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Where the metadata was evaluated from, if it was queried from a nixpkgs.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// What went wrong without keeping the metadata from being loaded, e.g. writing the cache.
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    /// Finds the metadata for a derivation by its store path, then by any of its output paths and
+    /// only then by `name`, which may pick the wrong package when several share a name.
+    pub fn find(&self, drv_path: &str, drv: &Drv) -> Option<&Package> {
+        let attribute_path = self
+            .by_drv_path
+            .get(drv_path)
+            .or_else(|| {
+                drv.outputs
+                    .values()
+                    .find_map(|v| self.by_out_path.get(&v.path))
+            })
+            .or_else(|| drv.env.get("name").and_then(|v| self.by_name.get(v)))?;
+
+        self.packages.get(attribute_path)
+    }
+}
+
+fn query_packages(nixpkgs: Option<&Path>) -> Result<Vec<u8>, Error> {
+    let mut command = Command::new("nix-env");
+    if let Some(path) = nixpkgs {
+        command.arg("-f").arg(path);
+    }
+
+    command_output(
+        command
+            .arg("-qa")
+            .arg(".*")
+            .arg("--json")
+            .arg("--meta")
+            .arg("--drv-path")
+            .arg("--out-path"),
+    )
+}
+
+fn parse_packages(data: &[u8], cache: Option<&Cache>) -> Result<HashMap<String, Package>, Error> {
+    serde_json::from_slice(data).map_err(|e| match cache {
+        Some(cache) => Error::json(
+            format!(
+                "the metadata cached in {}, try --refresh-cache",
+                cache.dir().display()
+            ),
+            e,
+        ),
+        None => Error::json("`nix-env -qa --json`", e),
+    })
+}
+
+/// Guesses the nixpkgs attribute paths of the packages in `graph` from their names, e.g. `hello`
+/// for hello-2.12 and `python39Packages.requests` for python3.9-requests-2.25.1. Sources are
+/// left out, they're never packages of their own.
+fn attribute_candidates(graph: &DerivationGraph) -> Vec<Vec<String>> {
+    let mut candidates = graph
+        .derivations
+        .values()
+        .filter(|drv| !is_fixed_output(drv))
+        .flat_map(|drv| {
+            let (name, _) = drv.name_and_version();
+            let drv_name = drv.env.get("name").map(String::as_str).unwrap_or_default();
+            let python = drv_name.split_once('-').and_then(|(prefix, _)| {
+                let version = prefix.strip_prefix("python").filter(|v| v.contains('.'))?;
+                let pname = name.strip_prefix(&format!("{}-", prefix)).unwrap_or(&name);
+                Some(vec![
+                    format!("python{}Packages", version.replace('.', "")),
+                    pname.to_string(),
+                ])
+            });
+
+            std::iter::once(vec![name.clone()]).chain(python)
+        })
+        .collect::<Vec<Vec<String>>>();
+    candidates.sort();
+    candidates.dedup();
+
+    candidates
+}
+
+/// Returns the commit a git checkout is at, without needing git itself.
+fn git_head(dir: &Path) -> Option<String> {
+    let head = fs::read_to_string(dir.join(".git/HEAD")).ok()?;
+    match head.trim().strip_prefix("ref: ") {
+        Some(reference) => fs::read_to_string(dir.join(".git").join(reference))
+            .ok()
+            .map(|v| v.trim().to_string()),
+        None => Some(head.trim().to_string()),
+    }
+}
+
+/// Identifies a nixpkgs on disk.
+fn path_id(path: &Path) -> Result<NixpkgsId, Error> {
+    // Channels are symlinks into the store, following them yields the actual revision
+    let path = fs::canonicalize(path)
+        .map_err(|e| Error::io(format!("resolving {}", path.display()), e))?;
+    let source = path.display().to_string();
+
+    if let Some(name) = source
+        .strip_prefix(&store_dir())
+        .and_then(|v| v.trim_start_matches('/').split('/').next())
+    {
+        // Store paths never change, their hash identifies the contents
+        return Ok(NixpkgsId {
+            key: store_path_hash(name).to_string(),
+            fingerprint: name.to_string(),
+            source,
+        });
+    }
+
+    // A checkout that may change under our feet, notice that by its commit or modification time
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    let fingerprint = git_head(&path).unwrap_or_else(|| {
+        fs::metadata(&path)
+            .and_then(|v| v.modified())
+            .ok()
+            .and_then(|v| v.duration_since(UNIX_EPOCH).ok())
+            .map(|v| v.as_secs().to_string())
+            .unwrap_or_default()
+    });
+
+    Ok(NixpkgsId {
+        key: format!("path-{:016x}", hasher.finish()),
+        fingerprint,
+        source,
+    })
+}
+
+impl Package {
+    pub(crate) fn license_expression(&self) -> Option<LicenseExpression> {
+        self.meta.license.as_ref().and_then(license::to_expression)
+    }
+
+    pub fn homepage(&self) -> Option<String> {
+        match self.meta.homepage.as_ref()? {
+            Homepages::Homepage(h) => Some(h.clone()),
+            // Assume if a list of URLs pick the first one
+            Homepages::HomepageList(h) => h.first().cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAKE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/flake");
+    const FLAKE_FOLLOWS: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/flake-follows");
+
+    fn locked(rev: &str, nar_hash: &str) -> Nixpkgs {
+        Nixpkgs::Flake {
+            reference: format!("github:NixOS/nixpkgs/{}", rev),
+            nar_hash: Some(nar_hash.to_string()),
+        }
+    }
+
+    #[test]
+    fn detects_locked_nixpkgs_of_flakes() {
+        let expected = locked(
+            "c06613c25df3fe1dd26243847a3c105cf6770627",
+            "sha256-e9L3TLLDVIJpMnHtiNHJE62oOh6emRtSZ244bgYJUZs=",
+        );
+
+        for input in [
+            FLAKE.to_string(),
+            format!("{}#hello", FLAKE),
+            format!("path:{}#hello", FLAKE),
+        ] {
+            let nixpkgs = Nixpkgs::detect(&Input::detect(&input)).unwrap();
+            assert_eq!(nixpkgs, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn detects_locked_nixpkgs_next_to_expressions() {
+        let input = Input::Expression(format!("{}/default.nix", FLAKE));
+
+        assert_eq!(
+            Nixpkgs::detect(&input).unwrap(),
+            locked(
+                "c06613c25df3fe1dd26243847a3c105cf6770627",
+                "sha256-e9L3TLLDVIJpMnHtiNHJE62oOh6emRtSZ244bgYJUZs=",
+            )
+        );
+    }
+
+    #[test]
+    fn follows_nixpkgs_inputs() {
+        // nixpkgs follows devshell/nixpkgs, not a node of its own
+        assert_eq!(
+            Nixpkgs::detect(&Input::detect(FLAKE_FOLLOWS)).unwrap(),
+            locked(
+                "3d2b4f2b3d1c5b9ef0b5a1a4eb1b1dbfc8a87c44",
+                "sha256-lhnzb3v1xMBG4Hs/0kbT8HdTv5Vk6fmTvMqy0nLF4Dc=",
+            )
+        );
+    }
+
+    #[test]
+    fn falls_back_to_nix_path_without_a_lock() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/store");
+        let input = Input::Expression(format!("{}/default.nix", dir));
+
+        assert_eq!(Nixpkgs::detect(&input).unwrap(), Nixpkgs::NixPath);
+    }
+}
@@ -0,0 +1,394 @@
+// Matching the packages of an SBOM against vulnerability advisories and nixpkgs' own
+// `knownVulnerabilities`.
+
+use crate::advisory::{package_keys, AdvisoryDb};
+use crate::derivation::{store_path_name, DerivationGraph, Drv};
+use crate::error::Error;
+use crate::identifiers::get_identifiers;
+use crate::nixpkgs::PackageIndex;
+
+/// Whether a vulnerability still applies to a package.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingStatus {
+    Vulnerable,
+    /// The derivation applies a patch named after the vulnerability.
+    Patched,
+}
+
+impl FindingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FindingStatus::Vulnerable => "vulnerable",
+            FindingStatus::Patched => "patched",
+        }
+    }
+}
+
+/// A vulnerability affecting one package of the SBOM.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub name: String,
+    pub version: String,
+    pub drv_path: String,
+    pub id: String,
+    pub aliases: Vec<String>,
+    pub summary: Option<String>,
+    /// Where the vulnerability is known from, `osv`, `nvd` or `nixpkgs`.
+    pub source: String,
+    pub status: FindingStatus,
+}
+
+/// Extracts the CVE ids mentioned in a string, e.g. a patch named `CVE-2021-22876.patch` or
+/// `cve_2021_22876.patch`.
+fn cve_ids(text: &str) -> Vec<String> {
+    let upper = text.to_uppercase();
+    let mut ids = Vec::new();
+    let mut rest = upper.as_str();
+
+    let is_separator = |text: &str, index: usize| {
+        text.as_bytes()
+            .get(index)
+            .is_some_and(|v| *v == b'-' || *v == b'_')
+    };
+
+    while let Some(start) = rest.find("CVE") {
+        let candidate = &rest[start + 3..];
+        if !is_separator(candidate, 0) {
+            rest = candidate;
+            continue;
+        }
+        let candidate = &candidate[1..];
+        let year = candidate
+            .get(..4)
+            .filter(|v| v.chars().all(|c| c.is_ascii_digit()));
+        let number = candidate
+            .get(5..)
+            .filter(|_| is_separator(candidate, 4))
+            .map(|v| {
+                v.chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect::<String>()
+            })
+            .filter(|v| v.len() >= 4);
+
+        if let (Some(year), Some(number)) = (year, number) {
+            ids.push(format!("CVE-{}-{}", year, number));
+        }
+        rest = candidate;
+    }
+
+    ids
+}
+
+/// CVEs fixed by the patches a derivation applies, going by the patch names.
+fn patched_cves(drv: &Drv) -> Vec<String> {
+    drv.env
+        .get("patches")
+        .into_iter()
+        .flat_map(|v| v.split_whitespace())
+        .flat_map(|v| cve_ids(store_path_name(v)))
+        .collect()
+}
+
+/// Adds a finding unless the package already has one for the same vulnerability, which is
+/// often in several databases under different ids.
+fn push_unique(findings: &mut Vec<Finding>, finding: Finding) {
+    let is_known = findings.iter().any(|v| {
+        v.id == finding.id || v.aliases.contains(&finding.id) || finding.aliases.contains(&v.id)
+    });
+
+    if !is_known {
+        findings.push(finding);
+    }
+}
+
+/// Matches every package in `graph` against `db` by name, upstream purl and CPE, and reports
+/// the `knownVulnerabilities` nixpkgs marks packages with. Sources aren't packages of their own
+/// and are skipped.
+pub(crate) fn scan(
+    graph: &DerivationGraph,
+    package_index: &PackageIndex,
+    db: &AdvisoryDb,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let mut paths = graph.derivations.keys().collect::<Vec<&String>>();
+    paths.sort();
+
+    for path in paths {
+        let drv = &graph.derivations[path];
+        let package = package_index.find(path, drv);
+        let identifiers = match get_identifiers(drv, package) {
+            Some(identifiers) => identifiers,
+            None => continue,
+        };
+        let (name, version) = match package {
+            Some(package) => (package.pname.clone(), package.version.clone()),
+            None => match drv.name_and_version() {
+                (name, Some(version)) => (name, version),
+                _ => continue,
+            },
+        };
+
+        let patched = patched_cves(drv);
+        let mut package_findings = Vec::new();
+        let finding = |id: String, aliases: Vec<String>, summary, source: &str| {
+            let is_patched = std::iter::once(&id)
+                .chain(&aliases)
+                .any(|v| patched.contains(v));
+
+            Finding {
+                name: name.clone(),
+                version: version.clone(),
+                drv_path: path.clone(),
+                id,
+                aliases,
+                summary,
+                source: source.to_string(),
+                status: if is_patched {
+                    FindingStatus::Patched
+                } else {
+                    FindingStatus::Vulnerable
+                },
+            }
+        };
+
+        let keys = package_keys(
+            &name,
+            identifiers.upstream_purl.as_deref(),
+            identifiers.cpe.as_deref(),
+        );
+        for advisory in db.candidates(&keys) {
+            if advisory.affects(&keys, &version) {
+                push_unique(
+                    &mut package_findings,
+                    finding(
+                        advisory.id.clone(),
+                        advisory.aliases.clone(),
+                        advisory.summary.clone(),
+                        advisory.source,
+                    ),
+                );
+            }
+        }
+
+        let known = package
+            .and_then(|v| v.meta.extra.get("knownVulnerabilities"))
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .flat_map(|v| v.as_str());
+        for description in known {
+            // Entries are free text, usually naming the CVE
+            let mut ids = cve_ids(description);
+            let id = if ids.is_empty() {
+                "knownVulnerabilities".to_string()
+            } else {
+                ids.remove(0)
+            };
+            push_unique(
+                &mut package_findings,
+                finding(id, ids, Some(description.to_string()), "nixpkgs"),
+            );
+        }
+
+        findings.extend(package_findings);
+    }
+
+    findings
+}
+
+pub fn findings_json(findings: &[Finding]) -> Result<String, Error> {
+    serde_json::to_string_pretty(findings).map_err(|e| Error::json("the findings", e))
+}
+
+/// Renders findings as a plain text table, one finding per line.
+pub fn findings_table(findings: &[Finding]) -> String {
+    const SUMMARY_WIDTH: usize = 60;
+
+    let rows = findings
+        .iter()
+        .map(|v| {
+            let summary = v.summary.as_deref().unwrap_or_default();
+            let summary = summary.lines().next().unwrap_or_default();
+            let summary = if summary.chars().count() > SUMMARY_WIDTH {
+                format!(
+                    "{}...",
+                    summary.chars().take(SUMMARY_WIDTH - 3).collect::<String>()
+                )
+            } else {
+                summary.to_string()
+            };
+
+            [
+                v.name.clone(),
+                v.version.clone(),
+                v.id.clone(),
+                v.status.as_str().to_string(),
+                v.source.clone(),
+                summary,
+            ]
+        })
+        .collect::<Vec<[String; 6]>>();
+
+    let header = ["PACKAGE", "VERSION", "ID", "STATUS", "SOURCE", "SUMMARY"].map(String::from);
+    let mut widths = header.clone().map(|v| v.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::path::Path;
+
+    const CLOSURE: &str = include_str!("../tests/fixtures/hello-closure.json");
+    const PACKAGES: &str = include_str!("../tests/fixtures/hello-packages.json");
+    const ADVISORIES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/advisories");
+    const HELLO: &str = "/nix/store/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10.drv";
+    const BASH: &str = "/nix/store/6cgi6gbpw7wxbwpf7kbq4xbxxs6s0yn1-bash-4.4-p23.drv";
+
+    fn graph() -> DerivationGraph {
+        DerivationGraph::new(HELLO.to_string(), serde_json::from_str(CLOSURE).unwrap())
+    }
+
+    fn package_index(packages: Value) -> PackageIndex {
+        PackageIndex::new(serde_json::from_value(packages).unwrap())
+    }
+
+    fn packages() -> Value {
+        serde_json::from_str(PACKAGES).unwrap()
+    }
+
+    fn db(files: &[&str]) -> AdvisoryDb {
+        let mut db = AdvisoryDb::new();
+        for file in files {
+            let path = Path::new(ADVISORIES).join(file);
+            if file.starts_with("osv") {
+                db.load_osv(&path).unwrap();
+            } else {
+                db.load_nvd(&path).unwrap();
+            }
+        }
+
+        db
+    }
+
+    fn ids(findings: &[Finding]) -> Vec<(&str, &str, &str)> {
+        findings
+            .iter()
+            .map(|v| (v.name.as_str(), v.id.as_str(), v.source.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn cve_ids_in_patch_names() {
+        assert_eq!(cve_ids("CVE-2021-22876.patch"), ["CVE-2021-22876"]);
+        assert_eq!(cve_ids("cve_2021_22876-fix.patch"), ["CVE-2021-22876"]);
+        assert_eq!(
+            cve_ids("fix-CVE-2021-22876-and-CVE-2021-22890.patch"),
+            ["CVE-2021-22876", "CVE-2021-22890"]
+        );
+        assert_eq!(cve_ids("CVE-2021-123456.patch"), ["CVE-2021-123456"]);
+    }
+
+    #[test]
+    fn cve_ids_need_a_year_and_four_digits() {
+        assert!(cve_ids("CVE-21-1234.patch").is_empty());
+        assert!(cve_ids("CVE-2021-123.patch").is_empty());
+        assert!(cve_ids("CVE-2021.patch").is_empty());
+        assert!(cve_ids("hello-greeting.patch").is_empty());
+    }
+
+    #[test]
+    fn patched_cves_go_by_store_path_names() {
+        let mut graph = graph();
+        let hello = graph.derivations.get_mut(HELLO).unwrap();
+        assert!(patched_cves(hello).is_empty());
+
+        hello.env.insert(
+            "patches".to_string(),
+            "/nix/store/a7pw5rb8hj3jgg3lrbzc0b2qvc0bp4wa-CVE-2019-1234.patch\n\
+             /nix/store/1wcl8g5fjgmyxzc3j0pq9nlsmrd66xwv-hello-greeting.patch \
+             /nix/store/9rxbbwxb8wbcvnkj4nl1y8fj5bpz8nk0-CVE-2020-0001_CVE-2020-0002.patch"
+                .to_string(),
+        );
+        assert_eq!(
+            patched_cves(hello),
+            ["CVE-2019-1234", "CVE-2020-0001", "CVE-2020-0002"]
+        );
+    }
+
+    #[test]
+    fn matches_packages_by_cpe() {
+        let findings = scan(&graph(), &package_index(packages()), &db(&["nvd-2.0.json"]));
+
+        assert_eq!(ids(&findings), [("bash", "CVE-2019-18276", "nvd")]);
+        assert_eq!(findings[0].version, "4.4-p23");
+        assert_eq!(findings[0].drv_path, BASH);
+        assert_eq!(findings[0].status, FindingStatus::Vulnerable);
+    }
+
+    #[test]
+    fn the_same_cve_is_reported_once() {
+        let mut packages = packages();
+        packages["bash"]["meta"]["knownVulnerabilities"] =
+            json!(["CVE-2019-18276: bash drops privileges incorrectly"]);
+        let db = db(&["osv.json", "nvd-2.0.json", "nvdcve-1.1.json"]);
+
+        let findings = scan(&graph(), &package_index(packages), &db);
+
+        assert_eq!(ids(&findings), [("bash", "OSV-2020-1612", "osv")]);
+        assert_eq!(findings[0].aliases, ["CVE-2019-18276"]);
+    }
+
+    #[test]
+    fn patched_vulnerabilities_are_reported_as_patched() {
+        let mut graph = graph();
+        graph.derivations.get_mut(BASH).unwrap().env.insert(
+            "patches".to_string(),
+            "/nix/store/a7pw5rb8hj3jgg3lrbzc0b2qvc0bp4wa-CVE-2019-18276.patch".to_string(),
+        );
+
+        let findings = scan(&graph, &package_index(packages()), &db(&["osv.json"]));
+
+        assert_eq!(ids(&findings), [("bash", "OSV-2020-1612", "osv")]);
+        assert_eq!(findings[0].status, FindingStatus::Patched);
+    }
+
+    #[test]
+    fn known_vulnerabilities_without_a_cve() {
+        let mut packages = packages();
+        packages["bash"]["meta"]["knownVulnerabilities"] = json!(["Unmaintained upstream"]);
+
+        let findings = scan(&graph(), &package_index(packages), &AdvisoryDb::new());
+
+        assert_eq!(
+            ids(&findings),
+            [("bash", "knownVulnerabilities", "nixpkgs")]
+        );
+        assert_eq!(
+            findings[0].summary.as_deref(),
+            Some("Unmaintained upstream")
+        );
+    }
+}
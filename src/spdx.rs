@@ -0,0 +1,600 @@
+// Building SPDX documents from a derivation graph.
+
+use crate::derivation::{
+    store_path_drv, store_path_hash, store_path_name, Closure, DerivationGraph, Drv, InputKind,
+};
+use crate::identifiers::{get_identifiers, Identifiers};
+use crate::license::ExtractedLicense;
+use crate::nixpkgs::PackageIndex;
+use crate::spdx_licenses;
+use crate::spdx_spec::Package as SPDXPackage;
+use crate::spdx_spec::*;
+use crate::DocumentInfo;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const NOASSERTION: &str = "NOASSERTION";
+
+impl Drv {
+    /// Describes the derivation with nothing but what the derivation itself tells us, for
+    /// anything that has no nixpkgs metadata such as fetchers and bootstrap tools.
+    fn get_spdx_package_info(&self) -> SPDXPackage {
+        let (name, version) = self.name_and_version();
+
+        SPDXPackage {
+            spdx_id: None,
+            annotations: None,
+            attribution_texts: None,
+            checksums: None,
+            comment: None,
+            copyright_text: None,
+            description: None,
+            download_location: Some(NOASSERTION.to_string()),
+            external_refs: None,
+            files_analyzed: Some(false), // TODO: Support file based SBOMs
+            has_files: None,
+            homepage: None,
+            license_comments: None,
+            license_concluded: None,
+            license_declared: None,
+            license_info_from_files: None,
+            name: Some(name),
+            originator: None,
+            package_file_name: self.out_path().map(|v| v.to_string()),
+            package_verification_code: None,
+            source_info: None,
+            summary: None,
+            supplier: None,
+            version_info: version,
+        }
+    }
+}
+
+/// Describes a local patch by its store path, it has neither a derivation nor a version.
+fn get_patch_package_info(graph: &DerivationGraph, path: &str) -> SPDXPackage {
+    let drv = store_path_drv(path);
+    let mut package = drv.get_spdx_package_info();
+    package.name = drv.env.get("name").cloned();
+    package.version_info = None;
+    package.checksums = Some(package_checksums(graph, &drv)).filter(|v| !v.is_empty());
+
+    package
+}
+
+trait SpdxPackages {
+    fn get_spdx_package_info_if_exists(&self, drv_path: &str, drv: &Drv) -> Option<SPDXPackage>;
+
+    fn get_spdx_package_info(&self, drv_path: &str, drv: &Drv) -> SPDXPackage {
+        self.get_spdx_package_info_if_exists(drv_path, drv)
+            .unwrap_or_else(|| drv.get_spdx_package_info())
+    }
+}
+
+impl SpdxPackages for PackageIndex {
+    fn get_spdx_package_info_if_exists(&self, drv_path: &str, drv: &Drv) -> Option<SPDXPackage> {
+        let package = self.find(drv_path, drv)?;
+        let license = package.license_expression().map(|v| v.expression);
+        let s = SPDXPackage {
+            spdx_id: None,
+            annotations: None,
+            attribution_texts: None,
+            checksums: None,
+            comment: None,
+            copyright_text: None,
+            description: package.meta.description.to_owned(),
+            download_location: Some(NOASSERTION.to_string()),
+            external_refs: None,
+            files_analyzed: Some(false), // TODO: Support file based SBOMs
+            has_files: None,
+            homepage: package.homepage(),
+            license_comments: None,
+            license_concluded: license.clone(),
+            license_declared: license,
+            license_info_from_files: None,
+            name: Some(package.pname.to_owned()),
+            originator: None,
+            package_file_name: drv.out_path().map(|v| v.to_string()),
+            package_verification_code: None,
+            source_info: None,
+            summary: None,
+            supplier: None,
+            version_info: Some(package.version.to_owned()),
+        };
+
+        Some(s)
+    }
+}
+
+fn spdx_algorithm(algorithm: &str) -> Option<Algorithm> {
+    match algorithm {
+        "md5" => Some(Algorithm::Md5),
+        "sha1" => Some(Algorithm::Sha1),
+        "sha256" => Some(Algorithm::Sha256),
+        "sha512" => Some(Algorithm::Sha512),
+        _ => None,
+    }
+}
+
+fn package_checksums(graph: &DerivationGraph, drv: &Drv) -> Vec<PackageChecksum> {
+    graph
+        .get_checksums(drv)
+        .into_iter()
+        .flat_map(|(algorithm, checksum_value)| {
+            Some(PackageChecksum {
+                algorithm: Some(spdx_algorithm(&algorithm)?),
+                checksum_value: Some(checksum_value),
+            })
+        })
+        .collect()
+}
+
+/// Prefixes an author with `Person: ` unless it already follows the SPDX creator syntax.
+fn creator(author: &str) -> String {
+    let is_creator = ["Person:", "Organization:", "Tool:"]
+        .iter()
+        .any(|v| author.starts_with(v));
+
+    if is_creator {
+        author.to_string()
+    } else {
+        format!("Person: {}", author)
+    }
+}
+
+/// SPDX specification versions the JSON document can be rendered as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpdxVersion {
+    V2_2,
+    V2_3,
+}
+
+impl SpdxVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpdxVersion::V2_2 => "SPDX-2.2",
+            SpdxVersion::V2_3 => "SPDX-2.3",
+        }
+    }
+}
+
+impl FromStr for SpdxVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2.2" => Ok(SpdxVersion::V2_2),
+            "2.3" => Ok(SpdxVersion::V2_3),
+            _ => Err(format!("unsupported SPDX version: {}", s)),
+        }
+    }
+}
+
+/// Builds an SPDX element id from a derivation store path, replacing anything outside of the
+/// allowed `[A-Za-z0-9.-]` character set. Keeping the store path hash in the id makes it both
+/// deterministic and unique, even for derivations that share a name.
+fn spdx_ref(path: &str) -> String {
+    let id: String = store_path_name(path)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    format!("SPDXRef-{}", id)
+}
+
+/// Percent-encodes a document name for use in the namespace URI, leaving only the unreserved
+/// characters of RFC 3986 as they are.
+fn uri_segment(name: &str) -> String {
+    name.bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{:02X}", b)
+            }
+        })
+        .collect()
+}
+
+fn relationship(
+    spdx_element_id: &str,
+    relationship_type: RelationshipType,
+    related_spdx_element: &str,
+) -> Relationship {
+    Relationship {
+        comment: None,
+        spdx_element_id: Some(spdx_element_id.to_string()),
+        related_spdx_element: Some(related_spdx_element.to_string()),
+        relationship_type: Some(relationship_type),
+    }
+}
+
+/// Relates an input to the derivation using it, by how it's used. A derivation is
+/// `GENERATED_FROM` its source, every other input is related to the derivation.
+fn input_relationships(
+    graph: &DerivationGraph,
+    drv_path: &str,
+    element: &str,
+    input_path: &str,
+    input: &str,
+) -> Vec<Relationship> {
+    let kind = match graph.closure {
+        Closure::Build => graph.input_kind(&graph.derivations[drv_path], input_path),
+        Closure::Runtime => {
+            return vec![relationship(
+                input,
+                RelationshipType::RuntimeDependencyOf,
+                element,
+            )]
+        }
+    };
+
+    match kind {
+        InputKind::BuildTool => vec![relationship(input, RelationshipType::BuildToolOf, element)],
+        InputKind::Dependency => vec![relationship(input, RelationshipType::DependencyOf, element)],
+        InputKind::Source => vec![relationship(
+            element,
+            RelationshipType::GeneratedFrom,
+            input,
+        )],
+        InputKind::Patch => patch_relationships(element, input),
+        InputKind::Other => vec![relationship(
+            input,
+            RelationshipType::BuildDependencyOf,
+            element,
+        )],
+    }
+}
+
+/// A patch is both `PATCH_FOR` the package and, being listed in `patches`, `PATCH_APPLIED` to it.
+fn patch_relationships(element: &str, patch: &str) -> Vec<Relationship> {
+    vec![
+        relationship(patch, RelationshipType::PatchFor, element),
+        relationship(patch, RelationshipType::PatchApplied, element),
+    ]
+}
+
+/// Walks the dependencies of every derivation in the graph and emits a `DEPENDS_ON` for each
+/// edge whose endpoints both made it into the document, along with a relationship telling how
+/// the input is used, see `input_relationships`. Local patches are related like fetched ones.
+/// The document itself `DESCRIBES` the root derivation.
+fn get_relationships(
+    graph: &DerivationGraph,
+    spdx_refs: &HashMap<String, String>,
+) -> Vec<Relationship> {
+    let mut relationships = Vec::new();
+
+    if let Some(root_ref) = spdx_refs.get(&graph.root) {
+        relationships.push(relationship(
+            "SPDXRef-DOCUMENT",
+            RelationshipType::Describes,
+            root_ref,
+        ));
+    }
+
+    let mut paths = graph.derivations.keys().collect::<Vec<&String>>();
+    paths.sort();
+
+    for path in paths {
+        let element = match spdx_refs.get(path) {
+            Some(element) => element,
+            None => continue,
+        };
+
+        for input_path in graph.dependencies(path) {
+            let input = match spdx_refs.get(input_path) {
+                Some(input) => input,
+                None => continue,
+            };
+
+            relationships.push(relationship(element, RelationshipType::DependsOn, input));
+            relationships.extend(input_relationships(graph, path, element, input_path, input));
+        }
+
+        for patch in graph.local_patches(&graph.derivations[path.as_str()]) {
+            let patch = spdx_ref(patch);
+            relationships.push(relationship(element, RelationshipType::DependsOn, &patch));
+            relationships.extend(patch_relationships(element, &patch));
+        }
+    }
+
+    relationships
+}
+
+fn external_refs(identifiers: Identifiers) -> Vec<ExternalRef> {
+    let external_ref = |category, reference_type: &str, locator| ExternalRef {
+        comment: None,
+        reference_category: Some(category),
+        reference_locator: Some(locator),
+        reference_type: Some(reference_type.to_string()),
+    };
+
+    let mut refs = vec![external_ref(
+        ReferenceCategory::PackageManager,
+        "purl",
+        identifiers.purl,
+    )];
+    if let Some(purl) = identifiers.upstream_purl {
+        refs.push(external_ref(
+            ReferenceCategory::PackageManager,
+            "purl",
+            purl,
+        ));
+    }
+    if let Some(cpe) = identifiers.cpe {
+        refs.push(external_ref(ReferenceCategory::Security, "cpe23Type", cpe));
+    }
+
+    refs
+}
+
+impl SpdxSchema {
+    pub(crate) fn new(
+        info: &DocumentInfo,
+        graph: &DerivationGraph,
+        package_index: &PackageIndex,
+    ) -> SpdxSchema {
+        let root = graph.root.as_str();
+        let derivations = &graph.derivations;
+        let spdx_version = info.spdx_version;
+        let creation_info = CreationInfo {
+            comment: package_index
+                .source()
+                .map(|v| format!("Package metadata evaluated from nixpkgs {}", v)),
+            created: Some(info.created.clone()),
+            creators: Some(info.authors.iter().map(|v| creator(v)).collect()),
+            license_list_version: Some(spdx_licenses::LICENSE_LIST_VERSION.to_string()),
+        };
+
+        let mut paths = derivations.keys().collect::<Vec<&String>>();
+        paths.sort();
+
+        let mut packages = Vec::new();
+        let mut spdx_refs = HashMap::new();
+        let mut extracted_licenses = HashMap::new();
+        for path in paths {
+            let drv = &derivations[path];
+            let mut package = package_index.get_spdx_package_info(path, drv);
+            let nix_package = package_index.find(path, drv);
+            package.spdx_id = Some(spdx_ref(path));
+            for license in nix_package
+                .and_then(|v| v.license_expression())
+                .map(|v| v.extracted)
+                .unwrap_or_default()
+            {
+                extracted_licenses.insert(license.license_id.clone(), license);
+            }
+            if let Some(identifiers) = get_identifiers(drv, nix_package) {
+                package.external_refs = Some(external_refs(identifiers));
+            }
+            if let Some(location) = graph.get_download_location(drv) {
+                package.download_location = Some(location.locator);
+            }
+            let checksums = package_checksums(graph, drv);
+            if !checksums.is_empty() {
+                package.checksums = Some(checksums);
+            }
+            spdx_refs.insert(path.clone(), spdx_ref(path));
+            packages.push(package);
+        }
+
+        let mut patches = derivations
+            .values()
+            .flat_map(|drv| graph.local_patches(drv))
+            .collect::<Vec<&str>>();
+        patches.sort();
+        patches.dedup();
+        packages.extend(patches.into_iter().map(|path| {
+            let mut package = get_patch_package_info(graph, path);
+            package.spdx_id = Some(spdx_ref(path));
+            package
+        }));
+        if spdx_version == SpdxVersion::V2_2 {
+            // SPDX 2.2 requires these even when nothing could be determined, 2.3 made them
+            // optional.
+            let noassertion = || Some(NOASSERTION.to_string());
+            for package in &mut packages {
+                package.license_concluded = package.license_concluded.take().or_else(noassertion);
+                package.license_declared = package.license_declared.take().or_else(noassertion);
+                package.copyright_text = package.copyright_text.take().or_else(noassertion);
+            }
+        }
+
+        let relationships = get_relationships(graph, &spdx_refs);
+
+        let mut extracted_licenses = extracted_licenses
+            .into_values()
+            .map(|v: ExtractedLicense| HasExtractedLicensingInfo {
+                comment: v.comment,
+                // nixpkgs doesn't carry license texts, the name is all there is to extract
+                extracted_text: Some(v.name.clone()),
+                license_id: Some(v.license_id),
+                name: Some(v.name),
+                see_alsos: v.see_also.map(|v| vec![v]),
+            })
+            .collect::<Vec<HasExtractedLicensingInfo>>();
+        extracted_licenses.sort_by(|a, b| a.license_id.cmp(&b.license_id));
+
+        let document_namespace = format!(
+            "{}/{}-{}",
+            info.namespace.trim_end_matches('/'),
+            uri_segment(&info.name),
+            store_path_hash(root)
+        );
+
+        let document = Document {
+            spdx_id: Some("SPDXRef-DOCUMENT".to_string()),
+            annotations: None,
+            comment: None,
+            creation_info: Some(creation_info),
+            data_license: Some(info.data_license.clone()),
+            document_namespace: Some(document_namespace),
+            document_describes: None,
+            external_document_refs: None,
+            files: None, // TODO: Support file based SBOMs
+            has_extracted_licensing_infos: Some(extracted_licenses).filter(|v| !v.is_empty()),
+            name: Some(info.name.clone()),
+            packages: Some(packages),
+            relationships: Some(relationships),
+            revieweds: None,
+            snippets: None,
+            spdx_version: Some(spdx_version.as_str().to_string()),
+        };
+
+        SpdxSchema { document }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spdx_tag_value::enum_name;
+
+    const CLOSURE: &str = include_str!("../tests/fixtures/hello-closure.json");
+    const HELLO: &str = "/nix/store/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10.drv";
+    const HELLO_REF: &str = "SPDXRef-0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10";
+    const PATCH_REF: &str = "SPDXRef-a7pw5rb8hj3jgg3lrbzc0b2qvc0bp4wa-hello-greeting.patch";
+
+    fn document(info: &DocumentInfo) -> Document {
+        let graph = DerivationGraph::new(HELLO.to_string(), serde_json::from_str(CLOSURE).unwrap());
+        SpdxSchema::new(info, &graph, &PackageIndex::new(HashMap::new())).document
+    }
+
+    fn info() -> DocumentInfo {
+        DocumentInfo::new("hello", vec!["Jane Doe".to_string()])
+    }
+
+    fn relationships(document: &Document) -> Vec<(String, String, String)> {
+        document
+            .relationships
+            .iter()
+            .flatten()
+            .map(|v| {
+                (
+                    v.spdx_element_id.clone().unwrap(),
+                    enum_name(v.relationship_type.as_ref().unwrap()),
+                    v.related_spdx_element.clone().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn relationship(
+        element: &str,
+        relationship_type: &str,
+        related: &str,
+    ) -> (String, String, String) {
+        (
+            element.to_string(),
+            relationship_type.to_string(),
+            related.to_string(),
+        )
+    }
+
+    #[test]
+    fn ids_are_derived_from_store_paths() {
+        let document = document(&info());
+        let mut ids = document
+            .packages
+            .iter()
+            .flatten()
+            .map(|v| v.spdx_id.clone().unwrap())
+            .collect::<Vec<String>>();
+        ids.sort();
+
+        assert_eq!(document.spdx_id.as_deref(), Some("SPDXRef-DOCUMENT"));
+        assert_eq!(
+            ids,
+            vec![
+                "SPDXRef-0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10",
+                "SPDXRef-3x7dwzq014bblazs7kq20p9hyzz0qh8g-hello-2.10.tar.gz",
+                "SPDXRef-6cgi6gbpw7wxbwpf7kbq4xbxxs6s0yn1-bash-4.4-p23",
+                "SPDXRef-a7pw5rb8hj3jgg3lrbzc0b2qvc0bp4wa-hello-greeting.patch",
+            ]
+        );
+        assert!(relationships(&document).contains(&relationship(
+            "SPDXRef-DOCUMENT",
+            "DESCRIBES",
+            HELLO_REF
+        )));
+    }
+
+    #[test]
+    fn ids_replace_disallowed_characters() {
+        assert_eq!(
+            spdx_ref("/nix/store/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-python3.9-a_b+c.drv"),
+            "SPDXRef-0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-python3.9-a-b-c"
+        );
+    }
+
+    #[test]
+    fn patches_are_patch_for_and_applied_to_the_package() {
+        let document = document(&info());
+        let relationships = relationships(&document);
+
+        for expected in [
+            relationship(HELLO_REF, "DEPENDS_ON", PATCH_REF),
+            relationship(PATCH_REF, "PATCH_FOR", HELLO_REF),
+            relationship(PATCH_REF, "PATCH_APPLIED", HELLO_REF),
+        ] {
+            assert!(relationships.contains(&expected), "{:?}", expected);
+        }
+        assert!(relationships.contains(&relationship(
+            HELLO_REF,
+            "GENERATED_FROM",
+            "SPDXRef-3x7dwzq014bblazs7kq20p9hyzz0qh8g-hello-2.10.tar.gz"
+        )));
+
+        let patch = document
+            .packages
+            .iter()
+            .flatten()
+            .find(|v| v.spdx_id.as_deref() == Some(PATCH_REF))
+            .unwrap();
+        assert_eq!(patch.name.as_deref(), Some("hello-greeting.patch"));
+        assert_eq!(patch.version_info, None);
+    }
+
+    #[test]
+    fn namespace_appends_the_encoded_name_and_root_hash() {
+        let mut info = info();
+        info.name = "my project/ünï".to_string();
+        info.namespace = "https://example.org/sboms/".to_string();
+
+        assert_eq!(
+            document(&info).document_namespace.as_deref(),
+            Some("https://example.org/sboms/my%20project%2F%C3%BCn%C3%AF-0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l")
+        );
+    }
+
+    #[test]
+    fn spdx_2_2_backfills_noassertion() {
+        let mut info = info();
+        info.spdx_version = SpdxVersion::V2_2;
+        let document = document(&info);
+
+        assert_eq!(document.spdx_version.as_deref(), Some("SPDX-2.2"));
+        for package in document.packages.iter().flatten() {
+            assert_eq!(package.license_concluded.as_deref(), Some(NOASSERTION));
+            assert_eq!(package.license_declared.as_deref(), Some(NOASSERTION));
+            assert_eq!(package.copyright_text.as_deref(), Some(NOASSERTION));
+        }
+    }
+
+    #[test]
+    fn spdx_2_3_leaves_unknowns_out() {
+        let document = document(&info());
+
+        assert_eq!(document.spdx_version.as_deref(), Some("SPDX-2.3"));
+        for package in document.packages.iter().flatten() {
+            assert_eq!(package.license_concluded, None);
+            assert_eq!(package.license_declared, None);
+            assert_eq!(package.copyright_text, None);
+        }
+    }
+}
@@ -15,19 +15,25 @@ extern crate serde_derive;
 use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct SpdxSchema {
-    #[serde(rename = "Document")]
-    pub document: Option<Document>,
+    pub document: Document,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Document {
+    /// Uniquely identify any element in an SPDX document which may be referenced by other
+    /// elements.
+    #[serde(rename = "SPDXID", skip_serializing_if = "Option::is_none")]
+    pub spdx_id: Option<String>,
     /// Provide additional information about an SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Vec<DocumentAnnotation>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// One instance is required for each SPDX file produced. It provides the necessary
     /// information for forward and backward compatibility for processing tools.
-    #[serde(rename = "creationInfo")]
+    #[serde(rename = "creationInfo", skip_serializing_if = "Option::is_none")]
     pub creation_info: Option<CreationInfo>,
     /// License expression for dataLicense.  Compliance with the SPDX specification includes
     /// populating the SPDX fields therein with data related to such fields ("SPDX-Metadata").
@@ -45,29 +51,45 @@ pub struct Document {
     /// merchantability, fitness for a particular purpose, non-infringement, or the absence of
     /// latent or other defects, accuracy, or the presence or absence of errors, whether or not
     /// discoverable, all to the greatest extent permissible under applicable law.
-    #[serde(rename = "dataLicense")]
+    #[serde(rename = "dataLicense", skip_serializing_if = "Option::is_none")]
     pub data_license: Option<String>,
+    /// The URI provides an unambiguous mechanism for other SPDX documents to reference SPDX
+    /// elements within this SPDX document.
+    #[serde(rename = "documentNamespace", skip_serializing_if = "Option::is_none")]
+    pub document_namespace: Option<String>,
     /// The describesPackage property relates an SpdxDocument to the package which it describes.
-    #[serde(rename = "describesPackages")]
-    pub describes_packages: Option<Vec<String>>,
+    #[serde(rename = "documentDescribes", skip_serializing_if = "Option::is_none")]
+    pub document_describes: Option<Vec<String>>,
     /// Identify any external SPDX documents referenced within this SPDX document.
-    #[serde(rename = "externalDocumentRefs")]
+    #[serde(
+        rename = "externalDocumentRefs",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub external_document_refs: Option<Vec<ExternalDocumentRef>>,
     /// Files referenced in the SPDX document
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub files: Option<Vec<File>>,
     /// Indicates that a particular ExtractedLicensingInfo was defined in the subject
     /// SpdxDocument.
-    #[serde(rename = "hasExtractedLicensingInfos")]
+    #[serde(
+        rename = "hasExtractedLicensingInfos",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub has_extracted_licensing_infos: Option<Vec<HasExtractedLicensingInfo>>,
     /// Identify name of this SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// Packages referenced in the SPDX document
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub packages: Option<Vec<Package>>,
     /// Relationships referenced in the SPDX document
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub relationships: Option<Vec<Relationship>>,
     /// Reviewed
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub revieweds: Option<Vec<Reviewed>>,
     /// Snippets referenced in the SPDX document
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub snippets: Option<Vec<Snippet>>,
     /// Provide a reference number that can be used to understand how to parse and interpret the
     /// rest of the file. It will enable both future changes to the specification and to support
@@ -75,7 +97,7 @@ pub struct Document {
     /// indicator. The major field will be incremented when incompatible changes between versions
     /// are made (one or more sections are created, modified or deleted). The minor field will be
     /// incremented when backwards compatible changes are made.
-    #[serde(rename = "spdxVersion")]
+    #[serde(rename = "spdxVersion", skip_serializing_if = "Option::is_none")]
     pub spdx_version: Option<String>,
 }
 
@@ -84,14 +106,16 @@ pub struct Document {
 pub struct DocumentAnnotation {
     /// Identify when the comment was made. This is to be specified according to the combined
     /// date and time in the UTC format, as specified in the ISO 8601 standard.
-    #[serde(rename = "annotationDate")]
+    #[serde(rename = "annotationDate", skip_serializing_if = "Option::is_none")]
     pub annotation_date: Option<String>,
     /// Type of the annotation.
-    #[serde(rename = "annotationType")]
+    #[serde(rename = "annotationType", skip_serializing_if = "Option::is_none")]
     pub annotation_type: Option<AnnotationType>,
     /// This field identifies the person, organization or tool that has commented on a file,
     /// package, or the entire document.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub annotator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
 }
 
@@ -99,11 +123,13 @@ pub struct DocumentAnnotation {
 /// information for forward and backward compatibility for processing tools.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreationInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// Identify when the SPDX file was originally created. The date is to be specified according
     /// to combined date and time in UTC format as specified in ISO 8601 standard. This field is
     /// distinct from the fields in section 8, which involves the addition of information during
     /// a subsequent review.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub created: Option<String>,
     /// Identify who (or what, in the case of a tool) created the SPDX file. If the SPDX file was
     /// created by an individual, indicate the person's name. If the SPDX file was created on
@@ -111,10 +137,11 @@ pub struct CreationInfo {
     /// created using a software tool, indicate the name and version for that tool. If multiple
     /// participants or tools were involved, use multiple instances of this field. Person name or
     /// organization name may be designated as “anonymous” if appropriate.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub creators: Option<Vec<String>>,
     /// An optional field for creators of the SPDX file to provide the version of the SPDX
     /// License List used when the SPDX file was created.
-    #[serde(rename = "licenseListVersion")]
+    #[serde(rename = "licenseListVersion", skip_serializing_if = "Option::is_none")]
     pub license_list_version: Option<String>,
 }
 
@@ -126,13 +153,14 @@ pub struct ExternalDocumentRef {
     /// changes to the content of the file will change its checksum. This class allows the
     /// results of a variety of checksum and cryptographic message digest algorithms to be
     /// represented.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub checksum: Option<ExternalDocumentRefChecksum>,
     /// externalDocumentId is a string containing letters, numbers, ., - and/or + which uniquely
     /// identifies an external document within this document.
-    #[serde(rename = "externalDocumentId")]
+    #[serde(rename = "externalDocumentId", skip_serializing_if = "Option::is_none")]
     pub external_document_id: Option<String>,
     /// SPDX ID for SpdxDocument.  A propoerty containing an SPDX document.
-    #[serde(rename = "spdxDocument")]
+    #[serde(rename = "spdxDocument", skip_serializing_if = "Option::is_none")]
     pub spdx_document: Option<String>,
 }
 
@@ -145,23 +173,25 @@ pub struct ExternalDocumentRefChecksum {
     /// Identifies the algorithm used to produce the subject Checksum. Currently, SHA-1 is the
     /// only supported algorithm. It is anticipated that other algorithms will be supported at a
     /// later time.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub algorithm: Option<Algorithm>,
     /// The checksumValue property provides a lower case hexidecimal encoded digest value
     /// produced using a specific algorithm.
-    #[serde(rename = "checksumValue")]
+    #[serde(rename = "checksumValue", skip_serializing_if = "Option::is_none")]
     pub checksum_value: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct File {
     /// Provide additional information about an SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Vec<FileAnnotation>>,
     /// Indicates the project in which the SpdxElement originated. Tools must preserve
     /// doap:homepage and doap:name properties and the URI (if one is known) of doap:Project
     /// resources that are values of this property. All other properties of doap:Projects are not
     /// directly supported by SPDX and may be dropped when translating to or from some SPDX
     /// formats.
-    #[serde(rename = "artifactOfs")]
+    #[serde(rename = "artifactOfs", skip_serializing_if = "Option::is_none")]
     pub artifact_ofs: Option<Vec<HashMap<String, Option<serde_json::Value>>>>,
     /// This field provides a place for the SPDX data creator to record acknowledgements that may
     /// be required to be communicated in some contexts. This is not meant to include theactual
@@ -169,46 +199,52 @@ pub struct File {
     /// include copyright notices (see also copyrightText). The SPDX data creator may use this
     /// field to record other acknowledgements, such as particular clauses from license texts,
     /// which may be necessary or desirable to reproduce.
-    #[serde(rename = "attributionTexts")]
+    #[serde(rename = "attributionTexts", skip_serializing_if = "Option::is_none")]
     pub attribution_texts: Option<Vec<String>>,
     /// The checksum property provides a mechanism that can be used to verify that the contents
     /// of a File or Package have not changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub checksums: Option<Vec<FileChecksum>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// The text of copyright declarations recited in the Package or File.
-    #[serde(rename = "copyrightText")]
+    #[serde(rename = "copyrightText", skip_serializing_if = "Option::is_none")]
     pub copyright_text: Option<String>,
     /// This field provides a place for the SPDX file creator to record file contributors.
     /// Contributors could include names of copyright holders and/or authors who may not be
     /// copyright holders yet contributed to the file content.
-    #[serde(rename = "fileContributors")]
+    #[serde(rename = "fileContributors", skip_serializing_if = "Option::is_none")]
     pub file_contributors: Option<Vec<String>>,
-    #[serde(rename = "fileDependencies")]
+    #[serde(rename = "fileDependencies", skip_serializing_if = "Option::is_none")]
     pub file_dependencies: Option<Vec<String>>,
     /// The name of the file relative to the root of the package.
-    #[serde(rename = "fileName")]
+    #[serde(rename = "fileName", skip_serializing_if = "Option::is_none")]
     pub file_name: Option<String>,
     /// The type of the file.
-    #[serde(rename = "fileTypes")]
+    #[serde(rename = "fileTypes", skip_serializing_if = "Option::is_none")]
     pub file_types: Option<Vec<FileType>>,
     /// The licenseComments property allows the preparer of the SPDX document to describe why the
     /// licensing in spdx:licenseConcluded was chosen.
-    #[serde(rename = "licenseComments")]
+    #[serde(rename = "licenseComments", skip_serializing_if = "Option::is_none")]
     pub license_comments: Option<String>,
     /// The licensing information that was discovered directly within the package. There will be
     /// an instance of this property for each distinct value of alllicenseInfoInFile properties
     /// of all files contained in the package.
-    #[serde(rename = "licenseInfoFromFiles")]
+    #[serde(
+        rename = "licenseInfoFromFiles",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub license_info_from_files: Option<Vec<String>>,
     /// Licensing information that was discovered directly in the subject file. This is also
     /// considered a declared license for the file.
-    #[serde(rename = "licenseInfoInFiles")]
+    #[serde(rename = "licenseInfoInFiles", skip_serializing_if = "Option::is_none")]
     pub license_info_in_files: Option<Vec<String>>,
     /// Identify name of this SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// This field provides a place for the SPDX file creator to record potential legal notices
     /// found in the file. This may or may not include copyright statements.
-    #[serde(rename = "noticeText")]
+    #[serde(rename = "noticeText", skip_serializing_if = "Option::is_none")]
     pub notice_text: Option<String>,
 }
 
@@ -217,14 +253,16 @@ pub struct File {
 pub struct FileAnnotation {
     /// Identify when the comment was made. This is to be specified according to the combined
     /// date and time in the UTC format, as specified in the ISO 8601 standard.
-    #[serde(rename = "annotationDate")]
+    #[serde(rename = "annotationDate", skip_serializing_if = "Option::is_none")]
     pub annotation_date: Option<String>,
     /// Type of the annotation.
-    #[serde(rename = "annotationType")]
+    #[serde(rename = "annotationType", skip_serializing_if = "Option::is_none")]
     pub annotation_type: Option<AnnotationType>,
     /// This field identifies the person, organization or tool that has commented on a file,
     /// package, or the entire document.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub annotator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
 }
 
@@ -237,10 +275,11 @@ pub struct FileChecksum {
     /// Identifies the algorithm used to produce the subject Checksum. Currently, SHA-1 is the
     /// only supported algorithm. It is anticipated that other algorithms will be supported at a
     /// later time.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub algorithm: Option<Algorithm>,
     /// The checksumValue property provides a lower case hexidecimal encoded digest value
     /// produced using a specific algorithm.
-    #[serde(rename = "checksumValue")]
+    #[serde(rename = "checksumValue", skip_serializing_if = "Option::is_none")]
     pub checksum_value: Option<String>,
 }
 
@@ -249,24 +288,31 @@ pub struct FileChecksum {
 /// rather than an ExtractedLicensingInfo.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HasExtractedLicensingInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// Verbatim license or licensing notice text that was discovered.
-    #[serde(rename = "extractedText")]
+    #[serde(rename = "extractedText", skip_serializing_if = "Option::is_none")]
     pub extracted_text: Option<String>,
     /// A human readable short form license identifier for a license. The license ID is iether on
     /// the standard license oist or the form "LicenseRef-"[idString] where [idString] is a
     /// unique string containing letters, numbers, ".", "-" or "+".
-    #[serde(rename = "licenseId")]
+    #[serde(rename = "licenseId", skip_serializing_if = "Option::is_none")]
     pub license_id: Option<String>,
     /// Identify name of this SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    #[serde(rename = "seeAlsos")]
+    #[serde(rename = "seeAlsos", skip_serializing_if = "Option::is_none")]
     pub see_alsos: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Package {
+    /// Uniquely identify any element in an SPDX document which may be referenced by other
+    /// elements.
+    #[serde(rename = "SPDXID", skip_serializing_if = "Option::is_none")]
+    pub spdx_id: Option<String>,
     /// Provide additional information about an SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Vec<PackageAnnotation>>,
     /// This field provides a place for the SPDX data creator to record acknowledgements that may
     /// be required to be communicated in some contexts. This is not meant to include theactual
@@ -274,78 +320,101 @@ pub struct Package {
     /// include copyright notices (see also copyrightText). The SPDX data creator may use this
     /// field to record other acknowledgements, such as particular clauses from license texts,
     /// which may be necessary or desirable to reproduce.
-    #[serde(rename = "attributionTexts")]
+    #[serde(rename = "attributionTexts", skip_serializing_if = "Option::is_none")]
     pub attribution_texts: Option<Vec<String>>,
     /// The checksum property provides a mechanism that can be used to verify that the contents
     /// of a File or Package have not changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub checksums: Option<Vec<PackageChecksum>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// The text of copyright declarations recited in the Package or File.
-    #[serde(rename = "copyrightText")]
+    #[serde(rename = "copyrightText", skip_serializing_if = "Option::is_none")]
     pub copyright_text: Option<String>,
     /// Provides a detailed description of the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// The URI at which this package is available for download. Private (i.e., not publicly
     /// reachable) URIs are acceptable as values of this property. The values
     /// http://spdx.org/rdf/terms#none and http://spdx.org/rdf/terms#noassertion may be used to
     /// specify that the package is not downloadable or that no attempt was made to determine its
     /// download location, respectively.
-    #[serde(rename = "downloadLocation")]
+    #[serde(rename = "downloadLocation", skip_serializing_if = "Option::is_none")]
     pub download_location: Option<String>,
     /// An External Reference allows a Package to reference an external source of additional
     /// information, metadata, enumerations, asset identifiers, or downloadable content believed
     /// to be relevant to the Package.
-    #[serde(rename = "externalRefs")]
+    #[serde(rename = "externalRefs", skip_serializing_if = "Option::is_none")]
     pub external_refs: Option<Vec<ExternalRef>>,
     /// Indicates whether the file content of this package has been available for or subjected to
     /// analysis when creating the SPDX document. If false indicates packages that represent
     /// metadata or URI references to a project, product, artifact, distribution or a component.
     /// If set to false, the package must not contain any files.
-    #[serde(rename = "filesAnalyzed")]
+    #[serde(rename = "filesAnalyzed", skip_serializing_if = "Option::is_none")]
     pub files_analyzed: Option<bool>,
     /// Indicates that a particular file belongs to a package.
-    #[serde(rename = "hasFiles")]
+    #[serde(rename = "hasFiles", skip_serializing_if = "Option::is_none")]
     pub has_files: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub homepage: Option<String>,
     /// The licenseComments property allows the preparer of the SPDX document to describe why the
     /// licensing in spdx:licenseConcluded was chosen.
-    #[serde(rename = "licenseComments")]
+    #[serde(rename = "licenseComments", skip_serializing_if = "Option::is_none")]
     pub license_comments: Option<String>,
+    /// License expression for licenseConcluded. The licensing that the preparer of this SPDX
+    /// document has concluded, based on the evidence, actually applies to the package.
+    #[serde(rename = "licenseConcluded", skip_serializing_if = "Option::is_none")]
+    pub license_concluded: Option<String>,
+    /// License expression for licenseDeclared. The licensing that the creators of the software
+    /// in the package, or the packager, have declared. Declarations by the original software
+    /// creator should be preferred, if they exist.
+    #[serde(rename = "licenseDeclared", skip_serializing_if = "Option::is_none")]
+    pub license_declared: Option<String>,
     /// The licensing information that was discovered directly within the package. There will be
     /// an instance of this property for each distinct value of alllicenseInfoInFile properties
     /// of all files contained in the package.
-    #[serde(rename = "licenseInfoFromFiles")]
+    #[serde(
+        rename = "licenseInfoFromFiles",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub license_info_from_files: Option<Vec<String>>,
     /// Identify name of this SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// The name and, optionally, contact information of the person or organization that
     /// originally created the package. Values of this property must conform to the agent and
     /// tool syntax.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub originator: Option<String>,
     /// The base name of the package file name. For example, zlib-1.2.5.tar.gz.
-    #[serde(rename = "packageFileName")]
+    #[serde(rename = "packageFileName", skip_serializing_if = "Option::is_none")]
     pub package_file_name: Option<String>,
     /// A manifest based verification code (the algorithm is defined in section 4.7 of the full
     /// specification) of the SPDX Item. This allows consumers of this data and/or database to
     /// determine if an SPDX item they have in hand is identical to the SPDX item from which the
     /// data was produced. This algorithm works even if the SPDX document is included in the SPDX
     /// item.
-    #[serde(rename = "packageVerificationCode")]
+    #[serde(
+        rename = "packageVerificationCode",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub package_verification_code: Option<PackageVerificationCode>,
     /// Allows the producer(s) of the SPDX document to describe how the package was acquired
     /// and/or changed from the original source.
-    #[serde(rename = "sourceInfo")]
+    #[serde(rename = "sourceInfo", skip_serializing_if = "Option::is_none")]
     pub source_info: Option<String>,
     /// Provides a short description of the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub summary: Option<String>,
     /// The name and, optionally, contact information of the person or organization who was the
     /// immediate supplier of this package to the recipient. The supplier may be different than
     /// originator when the software has been repackaged. Values of this property must conform to
     /// the agent and tool syntax.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub supplier: Option<String>,
     /// Provides an indication of the version of the package that is described by this
     /// SpdxDocument.
-    #[serde(rename = "versionInfo")]
+    #[serde(rename = "versionInfo", skip_serializing_if = "Option::is_none")]
     pub version_info: Option<String>,
 }
 
@@ -354,14 +423,16 @@ pub struct Package {
 pub struct PackageAnnotation {
     /// Identify when the comment was made. This is to be specified according to the combined
     /// date and time in the UTC format, as specified in the ISO 8601 standard.
-    #[serde(rename = "annotationDate")]
+    #[serde(rename = "annotationDate", skip_serializing_if = "Option::is_none")]
     pub annotation_date: Option<String>,
     /// Type of the annotation.
-    #[serde(rename = "annotationType")]
+    #[serde(rename = "annotationType", skip_serializing_if = "Option::is_none")]
     pub annotation_type: Option<AnnotationType>,
     /// This field identifies the person, organization or tool that has commented on a file,
     /// package, or the entire document.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub annotator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
 }
 
@@ -374,10 +445,11 @@ pub struct PackageChecksum {
     /// Identifies the algorithm used to produce the subject Checksum. Currently, SHA-1 is the
     /// only supported algorithm. It is anticipated that other algorithms will be supported at a
     /// later time.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub algorithm: Option<Algorithm>,
     /// The checksumValue property provides a lower case hexidecimal encoded digest value
     /// produced using a specific algorithm.
-    #[serde(rename = "checksumValue")]
+    #[serde(rename = "checksumValue", skip_serializing_if = "Option::is_none")]
     pub checksum_value: Option<String>,
 }
 
@@ -386,18 +458,19 @@ pub struct PackageChecksum {
 /// to be relevant to the Package.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExternalRef {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// Category for the external reference
-    #[serde(rename = "referenceCategory")]
+    #[serde(rename = "referenceCategory", skip_serializing_if = "Option::is_none")]
     pub reference_category: Option<ReferenceCategory>,
     /// The unique string with no spaces necessary to access the package-specific information,
     /// metadata, or content within the target location. The format of the locator is subject to
     /// constraints defined by the <type>.
-    #[serde(rename = "referenceLocator")]
+    #[serde(rename = "referenceLocator", skip_serializing_if = "Option::is_none")]
     pub reference_locator: Option<String>,
     /// Type of the external reference. These are definined in an appendix in the SPDX
     /// specification.
-    #[serde(rename = "referenceType")]
+    #[serde(rename = "referenceType", skip_serializing_if = "Option::is_none")]
     pub reference_type: Option<String>,
 }
 
@@ -413,39 +486,52 @@ pub struct PackageVerificationCode {
     /// SPDX file all SPDX files must be excluded from the package verification code. If this is
     /// not done it would be impossible to correctly calculate the verification codes in both
     /// files.
-    #[serde(rename = "packageVerificationCodeExcludedFiles")]
+    #[serde(
+        rename = "packageVerificationCodeExcludedFiles",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub package_verification_code_excluded_files: Option<Vec<String>>,
     /// The actual package verification code as a hex encoded value.
-    #[serde(rename = "packageVerificationCodeValue")]
+    #[serde(
+        rename = "packageVerificationCodeValue",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub package_verification_code_value: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Relationship {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
+    /// Id to which the SPDX element is related.
+    #[serde(rename = "spdxElementId", skip_serializing_if = "Option::is_none")]
+    pub spdx_element_id: Option<String>,
     /// SPDX ID for SpdxElement.  A related SpdxElement.
-    #[serde(rename = "relatedSpdxElement")]
+    #[serde(rename = "relatedSpdxElement", skip_serializing_if = "Option::is_none")]
     pub related_spdx_element: Option<String>,
     /// Describes the type of relationship between two SPDX elements.
-    #[serde(rename = "relationshipType")]
+    #[serde(rename = "relationshipType", skip_serializing_if = "Option::is_none")]
     pub relationship_type: Option<RelationshipType>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Reviewed {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// The date and time at which the SpdxDocument was reviewed. This value must be in UTC and
     /// have 'Z' as its timezone indicator.
-    #[serde(rename = "reviewDate")]
+    #[serde(rename = "reviewDate", skip_serializing_if = "Option::is_none")]
     pub review_date: Option<String>,
     /// The name and, optionally, contact information of the person who performed the review.
     /// Values of this property must conform to the agent and tool syntax.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reviewer: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Snippet {
     /// Provide additional information about an SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub annotations: Option<Vec<SnippetAnnotation>>,
     /// This field provides a place for the SPDX data creator to record acknowledgements that may
     /// be required to be communicated in some contexts. This is not meant to include theactual
@@ -453,32 +539,41 @@ pub struct Snippet {
     /// include copyright notices (see also copyrightText). The SPDX data creator may use this
     /// field to record other acknowledgements, such as particular clauses from license texts,
     /// which may be necessary or desirable to reproduce.
-    #[serde(rename = "attributionTexts")]
+    #[serde(rename = "attributionTexts", skip_serializing_if = "Option::is_none")]
     pub attribution_texts: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
     /// The text of copyright declarations recited in the Package or File.
-    #[serde(rename = "copyrightText")]
+    #[serde(rename = "copyrightText", skip_serializing_if = "Option::is_none")]
     pub copyright_text: Option<String>,
     /// The licenseComments property allows the preparer of the SPDX document to describe why the
     /// licensing in spdx:licenseConcluded was chosen.
-    #[serde(rename = "licenseComments")]
+    #[serde(rename = "licenseComments", skip_serializing_if = "Option::is_none")]
     pub license_comments: Option<String>,
     /// The licensing information that was discovered directly within the package. There will be
     /// an instance of this property for each distinct value of alllicenseInfoInFile properties
     /// of all files contained in the package.
-    #[serde(rename = "licenseInfoFromFiles")]
+    #[serde(
+        rename = "licenseInfoFromFiles",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub license_info_from_files: Option<Vec<String>>,
     /// Licensing information that was discovered directly in the subject snippet. This is also
     /// considered a declared license for the snippet.
-    #[serde(rename = "licenseInfoInSnippets")]
+    #[serde(
+        rename = "licenseInfoInSnippets",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub license_info_in_snippets: Option<Vec<String>>,
     /// Identify name of this SpdxElement.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// This field defines the byte range in the original host file (in X.2) that the snippet
     /// information applies to
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ranges: Option<Vec<Range>>,
     /// SPDX ID for File.  File containing the SPDX element (e.g. the file contaning a snippet).
-    #[serde(rename = "snippetFromFile")]
+    #[serde(rename = "snippetFromFile", skip_serializing_if = "Option::is_none")]
     pub snippet_from_file: Option<String>,
 }
 
@@ -487,34 +582,38 @@ pub struct Snippet {
 pub struct SnippetAnnotation {
     /// Identify when the comment was made. This is to be specified according to the combined
     /// date and time in the UTC format, as specified in the ISO 8601 standard.
-    #[serde(rename = "annotationDate")]
+    #[serde(rename = "annotationDate", skip_serializing_if = "Option::is_none")]
     pub annotation_date: Option<String>,
     /// Type of the annotation.
-    #[serde(rename = "annotationType")]
+    #[serde(rename = "annotationType", skip_serializing_if = "Option::is_none")]
     pub annotation_type: Option<AnnotationType>,
     /// This field identifies the person, organization or tool that has commented on a file,
     /// package, or the entire document.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub annotator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Range {
-    #[serde(rename = "endPointer")]
+    #[serde(rename = "endPointer", skip_serializing_if = "Option::is_none")]
     pub end_pointer: Option<EndPointer>,
-    #[serde(rename = "startPointer")]
+    #[serde(rename = "startPointer", skip_serializing_if = "Option::is_none")]
     pub start_pointer: Option<StartPointer>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EndPointer {
     /// SPDX ID for File
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reference: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StartPointer {
     /// SPDX ID for File
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reference: Option<String>,
 }
 
@@ -584,8 +683,10 @@ pub enum FileType {
 pub enum ReferenceCategory {
     #[serde(rename = "OTHER")]
     Other,
-    #[serde(rename = "PACKAGE_MANAGER")]
+    #[serde(rename = "PACKAGE_MANAGER", alias = "PACKAGE-MANAGER")]
     PackageManager,
+    #[serde(rename = "PERSISTENT_ID", alias = "PERSISTENT-ID")]
+    PersistentId,
     #[serde(rename = "SECURITY")]
     Security,
 }
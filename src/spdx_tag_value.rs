@@ -0,0 +1,456 @@
+use crate::spdx_spec::*;
+use serde::Serialize;
+
+/// Returns the serialized name of one of the `spdx_spec` enums, e.g. `DEPENDS_ON`.
+pub(crate) fn enum_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        _ => String::new(),
+    }
+}
+
+/// Tag-value spells the external reference categories with a hyphen where JSON has an
+/// underscore, e.g. `PACKAGE-MANAGER`.
+fn reference_category(category: &ReferenceCategory) -> String {
+    enum_name(category).replace('_', "-")
+}
+
+struct TagValueWriter {
+    out: String,
+}
+
+impl TagValueWriter {
+    fn tag(&mut self, tag: &str, value: &str) {
+        self.out.push_str(&format!("{}: {}\n", tag, value));
+    }
+
+    fn optional_tag(&mut self, tag: &str, value: &Option<String>) {
+        if let Some(value) = value {
+            self.tag(tag, value);
+        }
+    }
+
+    /// Free form text has to be wrapped in `<text>` once it spans multiple lines.
+    fn text(&mut self, tag: &str, value: &Option<String>) {
+        match value {
+            Some(value) if value.contains('\n') => {
+                self.tag(tag, &format!("<text>{}</text>", value))
+            }
+            Some(value) => self.tag(tag, value),
+            None => {}
+        }
+    }
+
+    fn list(&mut self, tag: &str, values: &Option<Vec<String>>) {
+        for value in values.iter().flatten() {
+            self.tag(tag, value);
+        }
+    }
+
+    fn section(&mut self, title: &str) {
+        self.out.push_str(&format!("\n##### {}\n\n", title));
+    }
+
+    fn annotations(&mut self, element: &str, annotations: &[Annotation]) {
+        for a in annotations {
+            self.optional_tag("Annotator", &a.annotator);
+            self.optional_tag("AnnotationDate", &a.annotation_date);
+            self.optional_tag("AnnotationType", &a.annotation_type);
+            self.tag("SPDXREF", element);
+            self.text("AnnotationComment", &a.comment);
+        }
+    }
+}
+
+/// Common view over the per element annotation types of the generated schema.
+struct Annotation {
+    annotator: Option<String>,
+    annotation_date: Option<String>,
+    annotation_type: Option<String>,
+    comment: Option<String>,
+}
+
+macro_rules! annotations {
+    ($annotations:expr) => {
+        $annotations
+            .iter()
+            .flatten()
+            .map(|a| Annotation {
+                annotator: a.annotator.clone(),
+                annotation_date: a.annotation_date.clone(),
+                annotation_type: a.annotation_type.as_ref().map(enum_name),
+                comment: a.comment.clone(),
+            })
+            .collect::<Vec<Annotation>>()
+    };
+}
+
+fn write_package(w: &mut TagValueWriter, package: &Package) {
+    w.section("Package");
+    w.optional_tag("PackageName", &package.name);
+    w.optional_tag("SPDXID", &package.spdx_id);
+    w.optional_tag("PackageVersion", &package.version_info);
+    w.optional_tag("PackageFileName", &package.package_file_name);
+    w.optional_tag("PackageSupplier", &package.supplier);
+    w.optional_tag("PackageOriginator", &package.originator);
+    w.optional_tag("PackageDownloadLocation", &package.download_location);
+    if let Some(files_analyzed) = package.files_analyzed {
+        w.tag("FilesAnalyzed", &files_analyzed.to_string());
+    }
+    if let Some(code) = &package.package_verification_code {
+        if let Some(value) = &code.package_verification_code_value {
+            let excluded = code
+                .package_verification_code_excluded_files
+                .iter()
+                .flatten()
+                .map(|v| format!(" (excludes: {})", v))
+                .collect::<String>();
+            w.tag("PackageVerificationCode", &format!("{}{}", value, excluded));
+        }
+    }
+    for checksum in package.checksums.iter().flatten() {
+        if let (Some(algorithm), Some(value)) = (&checksum.algorithm, &checksum.checksum_value) {
+            w.tag(
+                "PackageChecksum",
+                &format!("{}: {}", enum_name(algorithm), value),
+            );
+        }
+    }
+    w.optional_tag("PackageHomePage", &package.homepage);
+    w.text("PackageSourceInfo", &package.source_info);
+    w.optional_tag("PackageLicenseConcluded", &package.license_concluded);
+    w.list(
+        "PackageLicenseInfoFromFiles",
+        &package.license_info_from_files,
+    );
+    w.optional_tag("PackageLicenseDeclared", &package.license_declared);
+    w.text("PackageLicenseComments", &package.license_comments);
+    w.text("PackageCopyrightText", &package.copyright_text);
+    w.text("PackageSummary", &package.summary);
+    w.text("PackageDescription", &package.description);
+    w.text("PackageComment", &package.comment);
+    for external_ref in package.external_refs.iter().flatten() {
+        if let (Some(category), Some(reference_type), Some(locator)) = (
+            &external_ref.reference_category,
+            &external_ref.reference_type,
+            &external_ref.reference_locator,
+        ) {
+            w.tag(
+                "ExternalRef",
+                &format!(
+                    "{} {} {}",
+                    reference_category(category),
+                    reference_type,
+                    locator
+                ),
+            );
+            w.text("ExternalRefComment", &external_ref.comment);
+        }
+    }
+    for text in package.attribution_texts.iter().flatten() {
+        w.text("PackageAttributionText", &Some(text.clone()));
+    }
+    if let Some(spdx_id) = &package.spdx_id {
+        w.annotations(spdx_id, &annotations!(package.annotations));
+    }
+}
+
+fn write_file(w: &mut TagValueWriter, file: &File) {
+    w.section("File");
+    w.optional_tag("FileName", &file.file_name);
+    for file_type in file.file_types.iter().flatten() {
+        w.tag("FileType", &enum_name(file_type));
+    }
+    for checksum in file.checksums.iter().flatten() {
+        if let (Some(algorithm), Some(value)) = (&checksum.algorithm, &checksum.checksum_value) {
+            w.tag(
+                "FileChecksum",
+                &format!("{}: {}", enum_name(algorithm), value),
+            );
+        }
+    }
+    w.list("LicenseInfoInFile", &file.license_info_in_files);
+    w.text("LicenseComments", &file.license_comments);
+    w.text("FileCopyrightText", &file.copyright_text);
+    w.text("FileComment", &file.comment);
+    w.text("FileNotice", &file.notice_text);
+    w.list("FileContributor", &file.file_contributors);
+    for text in file.attribution_texts.iter().flatten() {
+        w.text("FileAttributionText", &Some(text.clone()));
+    }
+}
+
+impl SpdxSchema {
+    /// Renders the document in the SPDX tag-value (`.spdx`) format.
+    pub fn to_tag_value(&self) -> String {
+        let document = &self.document;
+        let mut w = TagValueWriter { out: String::new() };
+
+        w.optional_tag("SPDXVersion", &document.spdx_version);
+        w.optional_tag("DataLicense", &document.data_license);
+        w.optional_tag("SPDXID", &document.spdx_id);
+        w.optional_tag("DocumentName", &document.name);
+        w.optional_tag("DocumentNamespace", &document.document_namespace);
+        for external in document.external_document_refs.iter().flatten() {
+            if let (Some(id), Some(uri), Some(checksum)) = (
+                &external.external_document_id,
+                &external.spdx_document,
+                &external.checksum,
+            ) {
+                if let (Some(algorithm), Some(value)) =
+                    (&checksum.algorithm, &checksum.checksum_value)
+                {
+                    w.tag(
+                        "ExternalDocumentRef",
+                        &format!("{} {} {}: {}", id, uri, enum_name(algorithm), value),
+                    );
+                }
+            }
+        }
+        w.text("DocumentComment", &document.comment);
+
+        if let Some(creation_info) = &document.creation_info {
+            w.section("Creation Information");
+            w.list("Creator", &creation_info.creators);
+            w.optional_tag("Created", &creation_info.created);
+            w.optional_tag("LicenseListVersion", &creation_info.license_list_version);
+            w.text("CreatorComment", &creation_info.comment);
+        }
+
+        if let Some(spdx_id) = &document.spdx_id {
+            w.annotations(spdx_id, &annotations!(document.annotations));
+        }
+
+        for file in document.files.iter().flatten() {
+            write_file(&mut w, file);
+        }
+
+        for package in document.packages.iter().flatten() {
+            write_package(&mut w, package);
+        }
+
+        if let Some(infos) = &document.has_extracted_licensing_infos {
+            w.section("Other Licenses");
+            for info in infos {
+                w.optional_tag("LicenseID", &info.license_id);
+                w.tag(
+                    "ExtractedText",
+                    &format!(
+                        "<text>{}</text>",
+                        info.extracted_text.as_deref().unwrap_or_default()
+                    ),
+                );
+                w.optional_tag("LicenseName", &info.name);
+                w.list("LicenseCrossReference", &info.see_alsos);
+                w.text("LicenseComment", &info.comment);
+            }
+        }
+
+        if let Some(relationships) = &document.relationships {
+            w.section("Relationships");
+            for relationship in relationships {
+                if let (Some(element), Some(relationship_type), Some(related)) = (
+                    &relationship.spdx_element_id,
+                    &relationship.relationship_type,
+                    &relationship.related_spdx_element,
+                ) {
+                    w.tag(
+                        "Relationship",
+                        &format!("{} {} {}", element, enum_name(relationship_type), related),
+                    );
+                    w.text("RelationshipComment", &relationship.comment);
+                }
+            }
+        }
+
+        w.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    const DOCUMENT: &str = include_str!("../tests/fixtures/curl.spdx.json");
+
+    /// Splits a tag-value document back into its tags, joining `<text>` values spanning lines.
+    fn parse_tag_value(document: &str) -> Vec<(String, String)> {
+        let mut tags = Vec::new();
+        let mut lines = document.lines();
+
+        while let Some(line) = lines.next() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (tag, value) = line.split_once(": ").expect("tag without a value");
+            let mut value = value.to_string();
+            if let Some(text) = value.strip_prefix("<text>") {
+                let mut text = text.to_string();
+                while !text.ends_with("</text>") {
+                    text.push('\n');
+                    text.push_str(lines.next().expect("unterminated <text>"));
+                }
+                value = text.trim_end_matches("</text>").to_string();
+            }
+            tags.push((tag.to_string(), value));
+        }
+
+        tags
+    }
+
+    fn values<'a>(tags: &'a [(String, String)], tag: &str) -> Vec<&'a str> {
+        tags.iter()
+            .filter(|(t, _)| t == tag)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    fn render() -> (String, Value) {
+        let schema = serde_json::from_str::<SpdxSchema>(DOCUMENT).unwrap();
+        (
+            schema.to_tag_value(),
+            serde_json::to_value(&schema).unwrap(),
+        )
+    }
+
+    #[test]
+    fn document_fields_match_json() {
+        let (tag_value, json) = render();
+        let tags = parse_tag_value(&tag_value);
+
+        for (tag, field) in [
+            ("SPDXVersion", "spdxVersion"),
+            ("DataLicense", "dataLicense"),
+            ("SPDXID", "SPDXID"),
+            ("DocumentName", "name"),
+            ("DocumentNamespace", "documentNamespace"),
+        ] {
+            assert_eq!(
+                values(&tags, tag)[0],
+                json[field].as_str().unwrap(),
+                "{}",
+                tag
+            );
+        }
+
+        let creation_info = &json["creationInfo"];
+        let creators = creation_info["creators"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(values(&tags, "Creator"), creators);
+        assert_eq!(
+            values(&tags, "Created"),
+            [creation_info["created"].as_str().unwrap()]
+        );
+        assert_eq!(
+            values(&tags, "CreatorComment"),
+            [creation_info["comment"].as_str().unwrap()]
+        );
+    }
+
+    #[test]
+    fn package_fields_match_json() {
+        let (tag_value, json) = render();
+        let tags = parse_tag_value(&tag_value);
+        let packages = json["packages"].as_array().unwrap();
+
+        let field = |name: &str| {
+            packages
+                .iter()
+                .flat_map(|v| v[name].as_str())
+                .collect::<Vec<&str>>()
+        };
+        assert_eq!(values(&tags, "PackageName"), field("name"));
+        assert_eq!(values(&tags, "PackageVersion"), field("versionInfo"));
+        assert_eq!(
+            values(&tags, "PackageDownloadLocation"),
+            field("downloadLocation")
+        );
+        assert_eq!(
+            values(&tags, "PackageLicenseConcluded"),
+            field("licenseConcluded")
+        );
+        assert_eq!(values(&tags, "PackageDescription"), field("description"));
+        assert_eq!(values(&tags, "FilesAnalyzed"), ["false", "false"]);
+        // The document's own SPDXID comes first
+        assert_eq!(values(&tags, "SPDXID")[1..], field("SPDXID"));
+        assert_eq!(
+            values(&tags, "ExternalRef"),
+            ["PACKAGE-MANAGER purl pkg:nix/curl@7.76.1"]
+        );
+    }
+
+    #[test]
+    fn reference_categories_are_hyphenated() {
+        assert_eq!(
+            [
+                ReferenceCategory::PackageManager,
+                ReferenceCategory::PersistentId,
+                ReferenceCategory::Security,
+                ReferenceCategory::Other,
+            ]
+            .iter()
+            .map(reference_category)
+            .collect::<Vec<String>>(),
+            ["PACKAGE-MANAGER", "PERSISTENT-ID", "SECURITY", "OTHER"]
+        );
+    }
+
+    #[test]
+    fn multiline_text_is_wrapped() {
+        let (tag_value, json) = render();
+
+        let description = json["packages"][0]["description"].as_str().unwrap();
+        assert!(tag_value.contains(&format!(
+            "PackageDescription: <text>{}</text>\n",
+            description
+        )));
+        // Single line text stays as it is
+        let summary = json["packages"][0]["summary"].as_str().unwrap();
+        assert!(tag_value.contains(&format!("PackageSummary: {}\n", summary)));
+        assert!(tag_value.contains("ExtractedText: <text>Unfree</text>\n"));
+    }
+
+    #[test]
+    fn checksums_match_json() {
+        let (tag_value, json) = render();
+        let checksum = &json["packages"][1]["checksums"][0];
+
+        let line = format!(
+            "PackageChecksum: {}: {}\n",
+            checksum["algorithm"].as_str().unwrap(),
+            checksum["checksumValue"].as_str().unwrap()
+        );
+        assert!(line.starts_with("PackageChecksum: SHA256: "));
+        assert!(tag_value.contains(&line));
+        assert_eq!(tag_value.matches("PackageChecksum:").count(), 1);
+    }
+
+    #[test]
+    fn relationships_match_json() {
+        let (tag_value, json) = render();
+        let tags = parse_tag_value(&tag_value);
+
+        let relationships = json["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                format!(
+                    "{} {} {}",
+                    v["spdxElementId"].as_str().unwrap(),
+                    v["relationshipType"].as_str().unwrap(),
+                    v["relatedSpdxElement"].as_str().unwrap()
+                )
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(values(&tags, "Relationship"), relationships);
+        assert!(tag_value.contains(
+            "Relationship: SPDXRef-DOCUMENT DESCRIBES SPDXRef-0c1m4zj1ahdv6dvhv6ahcs2pm6mbh2ps-curl-7.76.1\n"
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+// Version comparison the way Nix does it in `builtins.compareVersions`, which is what nixpkgs
+// versions are written against.
+
+use std::cmp::Ordering;
+
+/// Splits off the next component of a version: a run of digits or a run of anything but digits
+/// and the `.`/`-` separators, which are skipped.
+fn next_component(version: &str) -> (&str, &str) {
+    let version = version.trim_start_matches(['.', '-']);
+    let end = if version.starts_with(|c: char| c.is_ascii_digit()) {
+        version.find(|c: char| !c.is_ascii_digit())
+    } else {
+        version.find(|c: char| c.is_ascii_digit() || c == '.' || c == '-')
+    }
+    .unwrap_or(version.len());
+
+    version.split_at(end)
+}
+
+fn is_number(component: &str) -> bool {
+    !component.is_empty() && component.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Orders two components like Nix: numbers numerically, `pre` before anything and anything
+/// else before numbers, so `2.3a` < `2.3.1`.
+fn compare_components(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let less = match (is_number(a), is_number(b)) {
+        (true, true) => {
+            let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+            return a.len().cmp(&b.len()).then_with(|| a.cmp(b));
+        }
+        _ if a.is_empty() && is_number(b) => true,
+        _ if a == "pre" => true,
+        _ if b == "pre" => false,
+        (_, true) => true,
+        (true, _) => false,
+        _ => a < b,
+    };
+
+    if less {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+/// Compares two versions component by component, a missing component counting as empty.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+
+    while !a.is_empty() || !b.is_empty() {
+        let (component_a, rest_a) = next_component(a);
+        let (component_b, rest_b) = next_component(b);
+
+        match compare_components(component_a, component_b) {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+        a = rest_a;
+        b = rest_b;
+    }
+
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_ordered(versions: &[&str]) {
+        for pair in versions.windows(2) {
+            assert_eq!(
+                compare_versions(pair[0], pair[1]),
+                Ordering::Less,
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+            assert_eq!(
+                compare_versions(pair[1], pair[0]),
+                Ordering::Greater,
+                "{} > {}",
+                pair[1],
+                pair[0]
+            );
+        }
+    }
+
+    #[test]
+    fn numbers_compare_numerically() {
+        assert_ordered(&["1.0", "1.0.1", "1.2", "1.9", "1.10", "2"]);
+        assert_eq!(compare_versions("1.01", "1.1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn pre_sorts_before_anything() {
+        assert_ordered(&["2.3pre1", "2.3pre2", "2.3", "2.3a"]);
+        assert_ordered(&["2.3pre", "2.3alpha"]);
+    }
+
+    #[test]
+    fn words_sort_before_numbers() {
+        assert_ordered(&["2.3", "2.3a", "2.3b", "2.3.1"]);
+        assert_ordered(&["4.4-p23", "4.4.1"]);
+    }
+
+    #[test]
+    fn separators_are_interchangeable() {
+        assert_eq!(compare_versions("4.4-p23", "4.4.p.23"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2.3", "1-2-3"), Ordering::Equal);
+        assert_eq!(compare_versions("", ""), Ordering::Equal);
+    }
+}
@@ -0,0 +1,81 @@
+// Runs the command line against the fixture store snapshot, with no nix on PATH.
+
+use serde_json::Value;
+use std::process::{Command, Output};
+
+const STORE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/store");
+const PACKAGES: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/hello-packages.json"
+);
+const HELLO: &str = "/nix/store/0f2hn4pxyqr1mfchkqsfpbgd0i2a8w0l-hello-2.10.drv";
+const SOURCE: &str = "/nix/store/jv9gi0wq6j2h5w8kz1qa5zj9m2z6ny2r-source.drv";
+
+fn nixbom(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nixbom"))
+        .args(["-n", "test", "-a", "Jane Doe", "--store-root", STORE])
+        .args(args)
+        .env("PATH", "/nonexistent")
+        .output()
+        .unwrap()
+}
+
+fn document(args: &[&str]) -> Value {
+    let output = nixbom(args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn package<'a>(document: &'a Value, name: &str) -> &'a Value {
+    document["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|v| v["name"] == name)
+        .unwrap_or_else(|| panic!("{} missing", name))
+}
+
+#[test]
+fn describes_derivations_alone_without_nix() {
+    let document = document(&["--metadata", "none", SOURCE]);
+    let source = package(&document, "source");
+
+    assert_eq!(
+        source["downloadLocation"],
+        "https://github.com/NixOS/patchelf/archive/0.12.tar.gz"
+    );
+    assert_eq!(
+        source["checksums"][0]["checksumValue"],
+        "4d2a1a5a3ef7b65de48b6ea38c88ec2cbb9e36ab9c7d2d93a4bcdb0bd5df81cc"
+    );
+    assert_eq!(source["licenseConcluded"], Value::Null);
+}
+
+#[test]
+fn reads_metadata_from_a_file_without_nix() {
+    let document = document(&["--metadata-file", PACKAGES, "--depth", "0", HELLO]);
+    let hello = package(&document, "hello");
+
+    assert_eq!(hello["versionInfo"], "2.10");
+    assert_eq!(hello["licenseConcluded"], "GPL-3.0-or-later");
+    assert_eq!(
+        hello["homepage"],
+        "https://www.gnu.org/software/hello/manual/"
+    );
+    // The local patch is described from the snapshot too
+    package(&document, "hello-greeting.patch");
+}
+
+#[test]
+fn querying_nixpkgs_needs_nix() {
+    // Without a cache nixpkgs isn't resolved first, nix-env is the first thing to run
+    let output = nixbom(&["--no-cache", SOURCE]);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`nix-env -qa"));
+}
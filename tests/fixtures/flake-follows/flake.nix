@@ -0,0 +1,9 @@
+{
+  inputs.utils.url = "github:numtide/flake-utils";
+  inputs.devshell.url = "github:numtide/devshell";
+  inputs.nixpkgs.follows = "devshell/nixpkgs";
+
+  outputs = { self, nixpkgs, utils, devshell }: {
+    packages.x86_64-linux.default = nixpkgs.legacyPackages.x86_64-linux.hello;
+  };
+}